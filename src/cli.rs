@@ -0,0 +1,58 @@
+use comp_rs::math::context::Context;
+use comp_rs::math::expr::Expression;
+use comp_rs::math::values::Value;
+use std::fs;
+use std::io::{stdin, BufRead};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: comp-rs --eval [EXPRESSION...] | --eval --file PATH | --eval < INPUT";
+
+/// Evaluates expressions without opening a window, printing one result per line.
+///
+/// Expressions come from the arguments, from a file given with `--file`, or from
+/// stdin when neither is given. Every expression shares one context, so earlier
+/// lines can define variables for later ones. Blank lines and lines starting
+/// with `#` are skipped.
+pub fn eval(args: &[String]) -> ExitCode {
+    let lines = match args {
+        [flag, path] if flag == "--file" => match fs::read_to_string(path) {
+            Ok(text) => text.lines().map(str::to_string).collect(),
+            Err(err) => {
+                eprintln!("unable to read `{}`: {}", path, err);
+                return ExitCode::FAILURE;
+            }
+        },
+        [flag, ..] if flag == "--file" => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+        [] => stdin().lock().lines().map_while(Result::ok).collect(),
+        _ => args.to_vec(),
+    };
+
+    let mut ctx = Context::default();
+    let mut failed = false;
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let result = match Expression::parse(line) {
+            Ok(expr) => {
+                ctx.reset_steps();
+                expr.eval(&mut ctx)
+            }
+            Err(err) => Value::Error(err),
+        };
+        if result.has_error() {
+            failed = true;
+        }
+        println!("{}", result);
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
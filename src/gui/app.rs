@@ -1,32 +1,207 @@
-use crate::gui::idx::new_id;
+use crate::math::idx::new_id;
+use crate::gui::dependencies::Dependencies;
+use crate::gui::jobs::{Progress, WorkerPool};
 use crate::gui::top::TopLevelExpression;
-use crate::math::context::{Context as MathContext, GLOBAL_MATH_CONTEXT};
+use crate::gui::worksheet::Worksheet;
+use crate::math::context::{Context as MathContext, Limits, GLOBAL_MATH_CONTEXT};
 use crate::math::expr::Expression;
 use crate::math::expr::Expression::GraphExpression;
-use crate::math::values::Value;
-use eframe::egui::{CentralPanel, Context, ScrollArea, SidePanel, Slider, SliderClamping, Ui};
+use crate::math::values::{Value, DIVISION_BY_ZERO};
+use eframe::egui::{
+    menu, CentralPanel, Color32, Context, DragValue, ProgressBar, ScrollArea, SidePanel, Slider,
+    SliderClamping, Spinner, TopBottomPanel, Ui, Window,
+};
 use eframe::epaint::Hsva;
 use eframe::{App, Frame};
 use egui_plot::{Line, PlotBounds, Points};
 use egui_plot::{Plot, PlotPoints, PlotUi};
 use num::complex::Complex64;
 use parking_lot::Mutex;
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 pub struct CalculatorApp {
     pub(crate) exprs: Vec<Arc<Mutex<TopLevelExpression>>>,
     pub complex_axis_input: f64,
-    pub expressions_cached: bool
+    /// The last known bounds of the graph: minimum X, maximum X, minimum Y, maximum Y.
+    pub plot_bounds: (f64, f64, f64, f64),
+    /// Bounds loaded from a worksheet, applied to the graph on the next frame.
+    pub restore_bounds: Option<(f64, f64, f64, f64)>,
+    pub worksheet_path: Option<PathBuf>,
+    pub file_dialog: Option<FileDialog>,
+    pub worksheet_error: Option<String>,
+    /// Which expressions read the variables defined by which others.
+    pub dependencies: Dependencies,
+    /// The threads computing answers and graphs.
+    pub pool: WorkerPool,
+    /// How deep and how long any one expression may evaluate.
+    pub limits: Limits,
+    /// A call depth still being dragged or typed in the settings. A new depth
+    /// restarts the workers, so it is only applied once the edit is finished.
+    pub depth_edit: Option<usize>,
+}
+
+/// A pending prompt for the path of a worksheet to open or save.
+pub enum FileDialog {
+    Open(String),
+    SaveAs(String),
+}
+
+impl Default for CalculatorApp {
+    fn default() -> CalculatorApp {
+        CalculatorApp {
+            exprs: vec![],
+            complex_axis_input: 0.0,
+            plot_bounds: (-5.0, 5.0, -5.0, 5.0),
+            restore_bounds: None,
+            worksheet_path: None,
+            file_dialog: None,
+            worksheet_error: None,
+            dependencies: Dependencies::default(),
+            pool: WorkerPool::new(Limits::default().stack_size()),
+            limits: Limits::default(),
+            depth_edit: None,
+        }
+    }
 }
 
 impl CalculatorApp {
-    fn render_left_panel(&mut self, ui: &mut Ui) {
-        let ctx = MathContext::default();
+    fn apply_depth_edit(&mut self) {
+        if let Some(max_depth) = self.depth_edit.take() {
+            self.limits.max_depth = max_depth;
+        }
+    }
+
+    fn render_menu_bar(&mut self, ui: &mut Ui) {
+        menu::bar(ui, |ui| {
+            ui.menu_button("File", |ui| {
+                if ui.button("Open...").clicked() {
+                    self.file_dialog = Some(FileDialog::Open(self.current_path()));
+                    ui.close_menu();
+                }
+                if ui.button("Save").clicked() {
+                    match self.worksheet_path.clone() {
+                        Some(path) => self.save_worksheet(path),
+                        None => self.file_dialog = Some(FileDialog::SaveAs(self.current_path())),
+                    }
+                    ui.close_menu();
+                }
+                if ui.button("Save As...").clicked() {
+                    self.file_dialog = Some(FileDialog::SaveAs(self.current_path()));
+                    ui.close_menu();
+                }
+            });
+            let settings = ui.menu_button("Settings", |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Maximum call depth");
+                    let mut max_depth = self.depth_edit.unwrap_or(self.limits.max_depth);
+                    let edit = ui.add(DragValue::new(&mut max_depth).range(1..=Limits::MAX_DEPTH));
+                    self.depth_edit = Some(max_depth);
+                    if !edit.dragged() && !edit.has_focus() {
+                        self.apply_depth_edit();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Maximum evaluation steps");
+                    ui.add(
+                        DragValue::new(&mut self.limits.max_steps)
+                            .range(1_000..=u64::MAX)
+                            .speed(10_000),
+                    );
+                });
+            });
+            // Closing the menu finishes an edit in progress.
+            if settings.inner.is_none() {
+                self.apply_depth_edit();
+            }
+            if let Some(err) = &self.worksheet_error {
+                ui.colored_label(Color32::RED, err);
+            }
+        });
+    }
+
+    fn render_file_dialog(&mut self, ctx: &Context) {
+        let Some(dialog) = &mut self.file_dialog else {
+            return;
+        };
+        let (title, path) = match dialog {
+            FileDialog::Open(path) => ("Open Worksheet", path),
+            FileDialog::SaveAs(path) => ("Save Worksheet As", path),
+        };
+
+        let mut confirmed = false;
+        let mut cancelled = false;
+        Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.text_edit_singleline(path);
+                ui.horizontal(|ui| {
+                    confirmed = ui.button("OK").clicked();
+                    cancelled = ui.button("Cancel").clicked();
+                });
+            });
+
+        if cancelled {
+            self.file_dialog = None;
+        } else if confirmed {
+            match self.file_dialog.take() {
+                Some(FileDialog::Open(path)) => self.open_worksheet(PathBuf::from(path)),
+                Some(FileDialog::SaveAs(path)) => self.save_worksheet(PathBuf::from(path)),
+                None => {}
+            }
+        }
+    }
+
+    fn current_path(&self) -> String {
+        self.worksheet_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "worksheet.json".to_string())
+    }
+
+    fn open_worksheet(&mut self, path: PathBuf) {
+        match Worksheet::load(&path) {
+            Ok(worksheet) => {
+                self.load_worksheet(worksheet);
+                self.worksheet_path = Some(path);
+                self.worksheet_error = None;
+            }
+            Err(err) => self.worksheet_error = Some(err),
+        }
+    }
+
+    fn save_worksheet(&mut self, path: PathBuf) {
+        match self.to_worksheet().save(&path) {
+            Ok(()) => {
+                self.worksheet_path = Some(path);
+                self.worksheet_error = None;
+            }
+            Err(err) => self.worksheet_error = Some(err),
+        }
+    }
 
+    /// The variables the expression at `index` reads from the rest of the worksheet.
+    /// That expression itself is not locked, as callers already hold it.
+    fn scope(&self, index: usize) -> MathContext {
+        let definitions: Vec<Option<Value>> = self
+            .exprs
+            .iter()
+            .enumerate()
+            .map(|(other, expr)| match other == index {
+                true => None,
+                false => expr.lock().definition.clone(),
+            })
+            .collect();
+        self.dependencies.context(index, &definitions, self.limits)
+    }
+
+    fn render_left_panel(&mut self, ui: &mut Ui) {
         let mut index = 0;
         let mut mark_remove: i32 = -1;
+        let mut mark_insert: Option<(usize, Expression)> = None;
 
         ui.vertical(|ui| {
             for mutex_expr in &self.exprs {
@@ -35,38 +210,19 @@ impl CalculatorApp {
                 expr.expression.update();
                 expr.expression.enforce_ooo();
 
-                let mut hasher = DefaultHasher::new();
-                expr.expression.hash(&mut hasher);
-                if hasher.finish() != expr.expression_hash {
-                    self.expressions_cached = false;
-                }
-
-                match &expr.expression {
-                    Expression::GraphExpression { .. } => {
+                match (&expr.problem, &expr.expression, &expr.answer_cached, &expr.job) {
+                    (Some(problem), _, _, _) => {
+                        ui.colored_label(Color32::RED, format!("= error: {}", problem));
+                    }
+                    (None, _, _, Some(job)) => render_progress(job, ui),
+                    (None, Expression::GraphExpression { .. }, _, None) => {
                         ui.label("= Check the graph!");
                     }
-                    _ => {
-                        if !self.expressions_cached {
-                            println!(
-                                "Reseting hash of {:?} {} vs {}",
-                                expr.expression,
-                                hasher.finish(),
-                                expr.expression_hash
-                            );
-                            expr.expression_hash = hasher.finish();
-                            expr.answer_cached = None;
-                        }
-                        if let Some(answer) = &expr.answer_cached {
-                            ui.label(format!("= {}", answer));
-                        } else {
-                            let async_expr = mutex_expr.clone();
-                            std::thread::spawn(move || {
-                                let mut expr = async_expr.lock();
-                                let answer = expr.expression.eval(&mut MathContext::default());
-                                expr.answer_cached = Some(answer);
-                            });
-                            ui.label("= Computing...");
-                        }
+                    (None, _, Some(answer), None) => {
+                        ui.label(format!("= {}", answer));
+                    }
+                    (None, _, None, None) => {
+                        ui.label("= Waiting for the expressions it depends on");
                     }
                 }
 
@@ -74,32 +230,48 @@ impl CalculatorApp {
                     ui.spacing();
                 });
 
-                if ui.button("Delete Expression").clicked() {
-                    mark_remove = index.clone();
-                }
+                ui.horizontal(|ui| {
+                    if ui.button("Copy").clicked() {
+                        ui.ctx().copy_text(expr.expression.to_string());
+                    }
+                    if ui.button("Simplify").clicked() {
+                        expr.expression = expr.expression.simplify(&self.scope(index as usize));
+                    }
+                    if ui.button("Expand").clicked() {
+                        mark_insert = Some((index as usize + 1, expr.expression.expand(&self.scope(index as usize))));
+                    }
+                    if ui.button("Factor").clicked() {
+                        mark_insert = Some((index as usize + 1, expr.expression.factor(&self.scope(index as usize))));
+                    }
+                    if ui.button("Delete Expression").clicked() {
+                        mark_remove = index;
+                    }
+                });
 
                 index += 1;
             }
 
             if mark_remove != -1 {
-                self.exprs.remove(mark_remove as usize);
+                self.exprs.remove(mark_remove as usize).lock().cancel_job();
+            }
+            if let Some((position, expression)) = mark_insert {
+                self.exprs
+                    .insert(position, Arc::new(Mutex::new(TopLevelExpression::new(expression))));
             }
 
             let add_btn = ui.button("+");
             if add_btn.clicked() {
-                self.exprs.push(Arc::new(Mutex::new(TopLevelExpression {
-                    expression: Expression::Literal {
+                self.exprs.push(Arc::new(Mutex::new(TopLevelExpression::new(
+                    Expression::Literal {
                         content: "".to_string(),
                         id: new_id(),
                         new_literal: true,
                     },
-                    expression_hash: u64::MAX,
-                    answer_cached: None,
-                    graph_cache: vec![],
-                    graph_data_cache: (0.0, 0.0, 0.0, 0.0, 0.0),
-                })));
+                ))));
             }
         });
+
+        self.update_dependencies();
     }
 
     fn render_plot(&mut self, ui: &mut Ui) {
@@ -119,17 +291,21 @@ impl CalculatorApp {
             if reset {
                 plot_ui.set_plot_bounds(PlotBounds::new_symmetrical(5.0));
             }
+            if let Some((min_x, max_x, min_y, max_y)) = self.restore_bounds.take() {
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max([min_x, min_y], [max_x, max_y]));
+            }
             let bounds = plot_ui.plot_bounds();
             let min_x = bounds.min()[0];
             let max_x = bounds.max()[0];
             let min_y = bounds.min()[1];
             let max_y = bounds.max()[1];
+            self.plot_bounds = (min_x, max_x, min_y, max_y);
 
             let steps: i32 = 5000;
             let step_dist = (max_x - min_x) / steps as f64;
             let cai = self.complex_axis_input;
 
-            for mutex_expr in &self.exprs {
+            for (index, mutex_expr) in self.exprs.iter().enumerate() {
                 let mut mutex_result = mutex_expr.lock();
                 let GraphExpression { ref expr } = mutex_result.expression.clone() else {
                     println!("expr {:?} is not graph", mutex_result.expression.clone());
@@ -138,8 +314,7 @@ impl CalculatorApp {
 
                 println!("got here for {:?}", expr);
 
-                let mut index = 0;
-                for point in &mutex_result.graph_cache {
+                for (index, point) in mutex_result.graph_cache.iter().enumerate() {
                     if index == 0 {
                         plot_ui.points(
                             Points::new(PlotPoints::new(vec![[point.0, point.1]])).color(point.2),
@@ -153,7 +328,6 @@ impl CalculatorApp {
                             ])).color(point.2),
                         );
                     }
-                    index += 1;
                 }
 
                 if mutex_result.graph_data_cache.0 != min_x
@@ -161,21 +335,39 @@ impl CalculatorApp {
                     || mutex_result.graph_data_cache.2 != min_y
                     || mutex_result.graph_data_cache.3 != max_y
                     || mutex_result.graph_data_cache.4 != cai
-                    || !self.expressions_cached
                 {
+                    // Wait until the variables the graph reads have been computed.
+                    if !mutex_result.up_to_date {
+                        continue;
+                    }
+                    if mutex_result.problem.is_some() {
+                        mutex_result.graph_cache.clear();
+                        continue;
+                    }
                     println!("regraphing");
-                    println!("math vars: {:?}", GLOBAL_MATH_CONTEXT.lock().unwrap().frames.last().unwrap().variables);
+                    println!("math vars: {:?}", GLOBAL_MATH_CONTEXT.lock().frames.last().unwrap().variables);
                     mutex_result.graph_data_cache = (min_x, max_x, min_y, max_y, cai);
+                    // Moving the view replaces a graph still being drawn for the old one.
+                    let (generation, progress) = mutex_result.start_job();
+                    progress.total.store(steps as usize, Ordering::Relaxed);
 
                     let cloned_mutex_expr = mutex_expr.clone();
+                    let failed_mutex_expr = mutex_expr.clone();
                     let cloned_expr = expr.clone();
+                    let mut scope = self.scope(index);
+                    scope.set_interrupt(progress.interrupt.clone());
 
-                    std::thread::spawn(move || {
+                    // The expression is graphed as written, since simplifying it could
+                    // fill in points where it is undefined, like `x / x` at 0.
+                    self.pool.submit(progress.clone(), move || {
                         let mut results = vec![];
                         println!("steps: {}", steps);
                         for step_count in 0..steps {
+                            if progress.interrupt.is_cancelled() {
+                                return;
+                            }
                             let x = min_x + (step_dist * step_count as f64);
-                            let mut ctx = MathContext::default();
+                            let mut ctx = scope.clone();
                             ctx.set_variable(
                                 "x".to_string(),
                                 Value::Number(Complex64::new(x, cai)),
@@ -187,14 +379,28 @@ impl CalculatorApp {
                                     color.h += (num.im / 10.0) as f32;
                                     results.push((x, num.re, color));
                                 }
+                                // A pole, like `1/x` at 0, leaves a gap rather than ending the graph.
+                                Value::Error(err) if err == DIVISION_BY_ZERO => {}
                                 Value::Error(err) => {
                                     println!("error: {}", err);
                                     break;
                                 },
                                 _ => {}
                             }
+                            progress.done.fetch_add(1, Ordering::Relaxed);
+                        }
+                        let mut expr = cloned_mutex_expr.lock();
+                        if expr.generation == generation {
+                            expr.graph_cache = results;
+                            expr.job = None;
+                        }
+                    }, move |err| {
+                        let mut expr = failed_mutex_expr.lock();
+                        if expr.generation == generation {
+                            expr.graph_cache.clear();
+                            expr.problem = Some(err);
+                            expr.job = None;
                         }
-                        cloned_mutex_expr.lock().graph_cache = results;
                     });
                 }
             }
@@ -204,9 +410,13 @@ impl CalculatorApp {
 
 impl App for CalculatorApp {
     fn update(&mut self, ctx: &Context, _frame: &mut Frame) {
-        self.expressions_cached = true;
         let start = Instant::now();
 
+        TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+            self.render_menu_bar(ui);
+        });
+        self.render_file_dialog(ctx);
+
         SidePanel::left("left_panel")
             .default_width(400.0)
             .resizable(true)
@@ -223,10 +433,38 @@ impl App for CalculatorApp {
         CentralPanel::default().show(ctx, |ui| {
             self.render_plot(ui);
         });
+
+        // Keep drawing while there is work in progress, so its results and
+        // progress show up without waiting for the next input.
+        let working = self.exprs.iter().any(|expr| {
+            let expr = expr.lock();
+            expr.job.is_some() || !expr.up_to_date
+        });
+        if working {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
         let end = Instant::now();
 
         // println!("render time: {:?}ms", (end - start).as_millis());
     }
 }
 
+fn render_progress(job: &Progress, ui: &mut Ui) {
+    if !job.started() {
+        ui.label("= Waiting for a free worker");
+        return;
+    }
+    match job.fraction() {
+        Some(fraction) => {
+            ui.add(ProgressBar::new(fraction).show_percentage());
+        }
+        None => {
+            ui.horizontal(|ui| {
+                ui.add(Spinner::new());
+                ui.label(format!("Computing, {} steps so far", job.interrupt.steps()));
+            });
+        }
+    }
+}
+
 fn render_plot_point(value: &Value, x: f64, ui: &mut PlotUi) {}
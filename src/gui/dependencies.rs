@@ -0,0 +1,331 @@
+use crate::gui::app::CalculatorApp;
+use crate::gui::jobs::WorkerPool;
+use crate::gui::top::TopLevelExpression;
+use crate::math::context::{Context as MathContext, Limits};
+use crate::math::expr::{BinaryOperation, Expression};
+use crate::math::values::Value;
+use parking_lot::Mutex;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// How the expressions of the side panel depend on each other through the
+/// variables they define and read.
+#[derive(Clone, Default)]
+pub struct Dependencies {
+    /// The variable each expression defines, if any.
+    pub defines: Vec<Option<String>>,
+    /// For every expression, the expressions defining the variables it reads.
+    pub direct: Vec<Vec<usize>>,
+    /// Every expression that can be computed, each one after those it depends on.
+    pub order: Vec<usize>,
+    /// Why an expression can not be computed, such as being part of a cycle.
+    pub problems: Vec<Option<String>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    New,
+    Active,
+    Done,
+}
+
+impl Dependencies {
+    pub fn new(exprs: &[Expression]) -> Dependencies {
+        let mut problems = vec![None; exprs.len()];
+        let mut defines = vec![None; exprs.len()];
+        let mut definitions: HashMap<&str, usize> = HashMap::new();
+        for (index, expr) in exprs.iter().enumerate() {
+            // Global `@` variables are shared through their own context instead.
+            let Some(name) = expr.defined_name().filter(|name| !name.starts_with('@')) else {
+                continue;
+            };
+            match definitions.get(name) {
+                Some(first) => {
+                    problems[index] = Some(format!("`{}` is already defined by expression {}", name, first + 1))
+                }
+                None => {
+                    definitions.insert(name, index);
+                    defines[index] = Some(name.to_string());
+                }
+            }
+        }
+        let direct = exprs
+            .iter()
+            .enumerate()
+            .map(|(index, expr)| {
+                reads(expr)
+                    .iter()
+                    .filter_map(|name| definitions.get(name.as_str()).copied())
+                    .filter(|&definition| definition != index)
+                    .collect()
+            })
+            .collect();
+
+        let mut dependencies = Dependencies {
+            defines,
+            direct,
+            order: vec![],
+            problems,
+        };
+        let mut visits = vec![Visit::New; exprs.len()];
+        let mut path = vec![];
+        for index in 0..exprs.len() {
+            dependencies.visit(index, &mut visits, &mut path);
+        }
+        dependencies
+    }
+
+    /// Orders the expression after everything it depends on, marking it when
+    /// it reaches back to an expression still on `path`.
+    fn visit(&mut self, index: usize, visits: &mut [Visit], path: &mut Vec<usize>) {
+        match visits[index] {
+            Visit::Done => return,
+            Visit::Active => {
+                let start = path.iter().position(|&step| step == index).unwrap();
+                let cycle: Vec<String> = path[start..]
+                    .iter()
+                    .chain([&index])
+                    .map(|&step| format!("`{}`", self.defines[step].as_deref().unwrap_or_default()))
+                    .collect();
+                for &step in &path[start..] {
+                    self.problems[step] = Some(format!("circular definition {}", cycle.join(" -> ")));
+                }
+                return;
+            }
+            Visit::New => {}
+        }
+
+        visits[index] = Visit::Active;
+        path.push(index);
+        for dependency in self.direct[index].clone() {
+            self.visit(dependency, visits, path);
+        }
+        path.pop();
+        visits[index] = Visit::Done;
+
+        if self.problems[index].is_some() {
+            return;
+        }
+        match self.direct[index].iter().find(|&&dependency| self.problems[dependency].is_some()) {
+            Some(&blocked) => {
+                self.problems[index] = Some(format!(
+                    "depends on `{}`, which can not be computed",
+                    self.defines[blocked].as_deref().unwrap_or_default()
+                ))
+            }
+            None => self.order.push(index),
+        }
+    }
+
+    /// Every expression `index` depends on, directly or through others.
+    pub fn upstream(&self, index: usize) -> BTreeSet<usize> {
+        let mut found = BTreeSet::new();
+        let mut pending = self.direct[index].clone();
+        while let Some(next) = pending.pop() {
+            if found.insert(next) {
+                pending.extend(&self.direct[next]);
+            }
+        }
+        found
+    }
+
+    /// Hashes every expression along with everything it depends on, so that
+    /// editing one changes the hashes of exactly the expressions downstream of it.
+    pub fn hashes(&self, expressions: &[Expression], limits: Limits) -> Vec<u64> {
+        let mut hashes = vec![0; expressions.len()];
+        for (index, problem) in self.problems.iter().enumerate() {
+            let mut hasher = DefaultHasher::new();
+            expressions[index].hash(&mut hasher);
+            problem.hash(&mut hasher);
+            limits.hash(&mut hasher);
+            hashes[index] = hasher.finish();
+        }
+        for &index in &self.order {
+            let mut hasher = DefaultHasher::new();
+            expressions[index].hash(&mut hasher);
+            limits.hash(&mut hasher);
+            for &dependency in &self.direct[index] {
+                hashes[dependency].hash(&mut hasher);
+            }
+            hashes[index] = hasher.finish();
+        }
+        hashes
+    }
+
+    /// Sets the variables `index` reads from other expressions, as far as they have been computed.
+    pub fn context(&self, index: usize, definitions: &[Option<Value>], limits: Limits) -> MathContext {
+        let mut ctx = MathContext::with_limits(limits);
+        for upstream in self.upstream(index) {
+            if let (Some(name), Some(value)) = (&self.defines[upstream], &definitions[upstream]) {
+                ctx.set_variable(name.clone(), value.clone());
+            }
+        }
+        ctx
+    }
+}
+
+/// The variables an expression reads from the rest of the worksheet.
+fn reads(expr: &Expression) -> BTreeSet<String> {
+    let mut names = match expr {
+        Expression::Binary {
+            op: BinaryOperation::Store,
+            lhs,
+            rhs,
+            ..
+        } => match lhs.function_signature() {
+            Some((_, variables)) => Expression::Lambda {
+                variables,
+                expr: rhs.clone(),
+            }
+            .free_variables(),
+            None => rhs.free_variables(),
+        },
+        // `x` is filled in by the graph itself.
+        Expression::GraphExpression { expr } => {
+            let mut names = expr.free_variables();
+            names.remove("x");
+            names
+        }
+        _ => expr.free_variables(),
+    };
+    // Functions may call themselves by name.
+    if let Some(name) = expr.defined_name() {
+        names.remove(name);
+    }
+    names
+}
+
+impl CalculatorApp {
+    /// Rebuilds the dependency graph, resets every expression that has changed
+    /// or depends on something that has, and starts computing each outdated
+    /// expression once everything it depends on has been computed.
+    pub fn update_dependencies(&mut self) {
+        let expressions: Vec<Expression> = self.exprs.iter().map(|expr| expr.lock().expression.clone()).collect();
+        let dependencies = Dependencies::new(&expressions);
+        let hashes = dependencies.hashes(&expressions, self.limits);
+
+        for (index, mutex_expr) in self.exprs.iter().enumerate() {
+            let mut expr = mutex_expr.lock();
+            if expr.expression_hash == hashes[index] {
+                continue;
+            }
+            expr.cancel_job();
+            expr.expression_hash = hashes[index];
+            expr.answer_cached = None;
+            expr.definition = None;
+            expr.problem = dependencies.problems[index].clone();
+            expr.up_to_date = expr.problem.is_some();
+        }
+        self.dependencies = dependencies;
+
+        // Workers are started with enough stack for the deepest calls allowed.
+        if self.pool.stack_size() != self.limits.stack_size() {
+            self.pool = WorkerPool::new(self.limits.stack_size());
+        }
+
+        let (up_to_date, definitions): (Vec<bool>, Vec<Option<Value>>) = self
+            .exprs
+            .iter()
+            .map(|expr| {
+                let expr = expr.lock();
+                (expr.up_to_date, expr.definition.clone())
+            })
+            .unzip();
+        for &index in &self.dependencies.order {
+            if up_to_date[index] || !self.dependencies.direct[index].iter().all(|&dependency| up_to_date[dependency]) {
+                continue;
+            }
+            let mutex_expr = &self.exprs[index];
+            let mut expr = mutex_expr.lock();
+            if expr.job.is_some() {
+                continue;
+            }
+            // Graphs are drawn by the plot once their dependencies are ready.
+            if let Expression::GraphExpression { .. } = expr.expression {
+                expr.graph_data_cache = (f64::NAN, f64::NAN, f64::NAN, f64::NAN, f64::NAN);
+                expr.up_to_date = true;
+                continue;
+            }
+
+            let (generation, progress) = expr.start_job();
+            let mut ctx = self.dependencies.context(index, &definitions, self.limits);
+            ctx.set_interrupt(progress.interrupt.clone());
+            let expression = expressions[index].clone();
+            let defines = self.dependencies.defines[index].is_some();
+            let (mutex_expr, failed_expr) = (mutex_expr.clone(), mutex_expr.clone());
+            self.pool.submit(
+                progress,
+                move || {
+                    let answer = expression.eval(&mut ctx);
+                    store_answer(&mutex_expr, generation, defines, answer);
+                },
+                move |err| store_answer(&failed_expr, generation, defines, Value::Error(err)),
+            );
+        }
+    }
+}
+
+/// Stores the answer of a job started as `generation`, unless a newer one has been started since.
+fn store_answer(mutex_expr: &Mutex<TopLevelExpression>, generation: u64, defines: bool, answer: Value) {
+    let mut expr = mutex_expr.lock();
+    if expr.generation != generation {
+        return;
+    }
+    if defines {
+        expr.definition = Some(answer.clone());
+    }
+    expr.answer_cached = Some(answer);
+    expr.up_to_date = true;
+    expr.job = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(sources: &[&str]) -> Vec<Expression> {
+        sources.iter().map(|source| Expression::parse(source).unwrap()).collect()
+    }
+
+    #[test]
+    fn orders_expressions_after_their_dependencies() {
+        let exprs = parse(&["e = d + c", "c = b * 2", "a = 1", "b = a + 1", "d = 5"]);
+        let dependencies = Dependencies::new(&exprs);
+        assert!(dependencies.problems.iter().all(Option::is_none));
+        assert_eq!(dependencies.order.len(), exprs.len());
+        let position = |index: usize| dependencies.order.iter().position(|&step| step == index).unwrap();
+        for (index, direct) in dependencies.direct.iter().enumerate() {
+            for &dependency in direct {
+                assert!(position(dependency) < position(index), "{} before {}", exprs[dependency], exprs[index]);
+            }
+        }
+        assert_eq!(dependencies.upstream(0), BTreeSet::from([1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn reports_cycles() {
+        let dependencies = Dependencies::new(&parse(&["a = b", "b = a", "c = a + 1", "d = 2"]));
+        assert_eq!(dependencies.problems[0].as_deref(), Some("circular definition `a` -> `b` -> `a`"));
+        assert_eq!(dependencies.problems[1].as_deref(), Some("circular definition `a` -> `b` -> `a`"));
+        assert_eq!(dependencies.problems[2].as_deref(), Some("depends on `a`, which can not be computed"));
+        assert_eq!(dependencies.problems[3], None);
+        assert_eq!(dependencies.order, vec![3]);
+    }
+
+    #[test]
+    fn edits_only_reach_expressions_downstream() {
+        let exprs = parse(&["a = 1", "b = a + 1", "c = b * 2", "d = 5", "e = d + c"]);
+        let hashes = |exprs: &[Expression]| Dependencies::new(exprs).hashes(exprs, Limits::default());
+        let before = hashes(&exprs);
+        let changed = |index: usize, source: &str| -> Vec<usize> {
+            let mut edited = exprs.clone();
+            edited[index] = Expression::parse(source).unwrap();
+            let after = hashes(&edited);
+            (0..exprs.len()).filter(|&other| before[other] != after[other]).collect()
+        };
+        assert_eq!(changed(0, "a = 2"), vec![0, 1, 2, 4]);
+        assert_eq!(changed(3, "d = 6"), vec![3, 4]);
+        assert_eq!(changed(4, "e = d - c"), vec![4]);
+        assert_eq!(hashes(&exprs), before);
+    }
+}
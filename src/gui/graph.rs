@@ -1,6 +1,6 @@
-use crate::gui::idx::new_id;
+use crate::math::idx::new_id;
 use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
-use eframe::egui::{Color32, ComboBox, Frame, Id, Response, Sense, Stroke, TextEdit, Ui, Vec2};
+use eframe::egui::{Color32, ComboBox, Frame, Grid, Id, Response, Sense, Stroke, TextEdit, Ui, Vec2};
 
 impl Expression {
     pub fn render(&mut self, ui: &mut Ui) -> Response {
@@ -12,8 +12,13 @@ impl Expression {
             } => generate_frame(ui, |ui| {
                 generate_frame(ui, |ui| {
                     ui.horizontal(|ui| {
-                        generate_unop_box(ui, operation, *id);
-                        expr.render(ui);
+                        if *operation == UnaryOperation::Factorial {
+                            expr.render(ui);
+                            generate_unop_box(ui, operation, *id);
+                        } else {
+                            generate_unop_box(ui, operation, *id);
+                            expr.render(ui);
+                        }
                     });
                 });
             }),
@@ -26,10 +31,28 @@ impl Expression {
                             rhs.render(ui);
                         });
                     }),
-                    BinaryOperation::Invoke => generate_frame(ui, |ui| {
+                    BinaryOperation::Log => generate_frame(ui, |ui| {
                         ui.horizontal(|ui| {
+                            generate_binop_box(ui, op, *id);
                             lhs.render(ui);
+                            ui.label("(");
+                            rhs.render(ui);
+                            ui.label(")");
+                        });
+                    }),
+                    op if op.is_function() => generate_frame(ui, |ui| {
+                        ui.horizontal(|ui| {
                             generate_binop_box(ui, op, *id);
+                            ui.label("(");
+                            lhs.render(ui);
+                            ui.label(",");
+                            rhs.render(ui);
+                            ui.label(")");
+                        });
+                    }),
+                    BinaryOperation::Invoke => generate_frame(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            render_call(ui, lhs, op, *id);
                             rhs.render(ui);
                             ui.label(")");
                         });
@@ -44,7 +67,7 @@ impl Expression {
                 }
             }
             Expression::Literal { content, id, new_literal } => {
-                let len = content.len().clone();
+                let len = content.len();
                 let mut text_edit = TextEdit::singleline(content);
                 text_edit = text_edit.id(Id::new(*id));
 
@@ -79,6 +102,7 @@ impl Expression {
                     }
                 });
             }),
+            Expression::Vector { exprs, id } if is_matrix(exprs) => render_matrix(ui, exprs, *id),
             Expression::Vector { exprs, id } => render_vec(ui, exprs),
             Expression::GraphExpression { expr } => generate_frame(ui, |ui| {
                 ui.label("Graph f(x)=");
@@ -90,10 +114,65 @@ impl Expression {
                 variable,
                 expression,
             } => render_summation(ui, minimum, maximum, variable, expression),
-            Expression::Lambda { variable, expr } => generate_frame(ui, |ui| {
+            Expression::Product {
+                minimum,
+                maximum,
+                variable,
+                expression,
+            } => render_product(ui, minimum, maximum, variable, expression),
+            Expression::Solve {
+                minimum,
+                maximum,
+                variable,
+                equation,
+            } => render_solve(ui, minimum, maximum, variable, equation),
+            Expression::Integral {
+                lower,
+                upper,
+                variable,
+                integrand,
+            } => render_integral(ui, lower, upper, variable, integrand),
+            Expression::Conditional {
+                condition,
+                consequent,
+                alternative,
+            } => generate_frame(ui, |ui| {
                 ui.horizontal(|ui| {
-                    ui.label("fn(");
+                    ui.label("if");
+                    condition.render(ui);
+                    ui.label("then");
+                    consequent.render(ui);
+                    ui.label("else");
+                    alternative.render(ui);
+                });
+            }),
+            Expression::Roots {
+                polynomial,
+                variable,
+            } => generate_frame(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Roots of");
+                    polynomial.render(ui);
+                    ui.label("in");
+                    variable.render(ui);
+                });
+            }),
+            Expression::Derivative {
+                variable,
+                expression,
+            } => generate_frame(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("d/d");
                     variable.render(ui);
+                    ui.label("(");
+                    expression.render(ui);
+                    ui.label(")");
+                });
+            }),
+            Expression::Lambda { variables, expr } => generate_frame(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("fn(");
+                    render_parameters(ui, variables);
                     ui.label(") = ");
                     expr.render(ui);
                 });
@@ -102,13 +181,58 @@ impl Expression {
     }
 }
 
+/// Renders the function and all but the last argument of a curried call,
+/// so `f(a)(b)` shows up as `f(a, b)`.
+fn render_call(ui: &mut Ui, function: &mut Expression, op: &mut BinaryOperation, id: u64) {
+    match function {
+        Expression::Binary {
+            op: inner @ BinaryOperation::Invoke,
+            lhs,
+            rhs,
+            id: inner_id,
+        } => {
+            render_call(ui, lhs, inner, *inner_id);
+            rhs.render(ui);
+            ui.label(",");
+        }
+        _ => {
+            function.render(ui);
+            generate_binop_box(ui, op, id);
+        }
+    }
+}
+
+fn render_parameters(ui: &mut Ui, variables: &mut Vec<Expression>) {
+    let mut remove = None;
+    let len = variables.len();
+    for (index, variable) in variables.iter_mut().enumerate() {
+        variable.render(ui);
+        if len > 1 && ui.small_button("-").clicked() {
+            remove = Some(index);
+        }
+        if index + 1 != len {
+            ui.label(",");
+        }
+    }
+    if let Some(index) = remove {
+        variables.remove(index);
+    }
+    if ui.small_button("+").clicked() {
+        variables.push(Expression::Literal {
+            content: "".to_string(),
+            id: new_id(),
+            new_literal: true,
+        });
+    }
+}
+
 fn render_vec(ui: &mut Ui, exprs: &mut Vec<Expression>) -> Response {
     generate_frame(ui, |ui| {
         ui.horizontal(|ui| {
             ui.label("[");
             let mut index = 0;
             let mut remove = -1;
-            let len = exprs.len().clone();
+            let len = exprs.len();
             for expr in &mut *exprs {
                 expr.render(ui);
                 index += 1;
@@ -134,6 +258,86 @@ fn render_vec(ui: &mut Ui, exprs: &mut Vec<Expression>) -> Response {
     })
 }
 
+/// Whether the entries of a vector are the rows of a matrix, with at least one column.
+fn is_matrix(rows: &[Expression]) -> bool {
+    let Some(Expression::Vector { exprs: first, .. }) = rows.first() else {
+        return false;
+    };
+    !first.is_empty()
+        && rows
+            .iter()
+            .all(|row| matches!(row, Expression::Vector { exprs, .. } if exprs.len() == first.len()))
+}
+
+/// Shows the rows of a matrix as a grid, with buttons to add and remove
+/// whole rows and columns so it stays rectangular.
+fn render_matrix(ui: &mut Ui, rows: &mut Vec<Expression>, id: u64) -> Response {
+    let empty = || Expression::Literal {
+        content: "".to_string(),
+        id: new_id(),
+        new_literal: false,
+    };
+    generate_frame(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("[");
+            let mut remove_row = None;
+            let mut remove_column = None;
+            let mut add_row = false;
+            let mut add_column = false;
+            ui.vertical(|ui| {
+                Grid::new(Id::new(id).with("matrix")).show(ui, |ui| {
+                    let mut columns = 0;
+                    for (index, row) in rows.iter_mut().enumerate() {
+                        let Expression::Vector { exprs, .. } = row else {
+                            continue;
+                        };
+                        columns = exprs.len();
+                        for entry in exprs {
+                            entry.render(ui);
+                        }
+                        if ui.small_button("-").clicked() {
+                            remove_row = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                    for column in 0..columns {
+                        if ui.small_button("-").clicked() {
+                            remove_column = Some(column);
+                        }
+                    }
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    add_row = ui.button("+ Row").clicked();
+                    add_column = ui.button("+ Column").clicked();
+                });
+            });
+            ui.label("]");
+
+            if let Some(index) = remove_row {
+                rows.remove(index);
+            }
+            for row in rows.iter_mut() {
+                let Expression::Vector { exprs, .. } = row else {
+                    continue;
+                };
+                if let Some(column) = remove_column {
+                    exprs.remove(column);
+                }
+                if add_column {
+                    exprs.push(empty());
+                }
+            }
+            if add_row {
+                if let Some(Expression::Vector { exprs, .. }) = rows.first() {
+                    let exprs = exprs.iter().map(|_| empty()).collect();
+                    rows.push(Expression::Vector { exprs, id: new_id() });
+                }
+            }
+        });
+    })
+}
+
 fn render_summation(
     ui: &mut Ui,
     minimum: &mut Expression,
@@ -158,6 +362,73 @@ fn render_summation(
     })
 }
 
+fn render_product(
+    ui: &mut Ui,
+    minimum: &mut Expression,
+    maximum: &mut Expression,
+    variable: &mut Expression,
+    expression: &mut Expression,
+) -> Response {
+    generate_frame(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                maximum.render(ui);
+                ui.label("Product");
+                ui.horizontal(|ui| {
+                    variable.render(ui);
+                    ui.label("=");
+                    minimum.render(ui);
+                });
+            });
+            ui.label("=");
+            expression.render(ui);
+        });
+    })
+}
+
+fn render_integral(
+    ui: &mut Ui,
+    lower: &mut Expression,
+    upper: &mut Expression,
+    variable: &mut Expression,
+    integrand: &mut Expression,
+) -> Response {
+    generate_frame(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                upper.render(ui);
+                ui.label("Integral");
+                lower.render(ui);
+            });
+            integrand.render(ui);
+            ui.label("d");
+            variable.render(ui);
+        });
+    })
+}
+
+fn render_solve(
+    ui: &mut Ui,
+    minimum: &mut Expression,
+    maximum: &mut Expression,
+    variable: &mut Expression,
+    equation: &mut Expression,
+) -> Response {
+    generate_frame(ui, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Solve");
+            equation.render(ui);
+            ui.label("for");
+            variable.render(ui);
+            ui.label("in [");
+            minimum.render(ui);
+            ui.label(",");
+            maximum.render(ui);
+            ui.label("]");
+        });
+    })
+}
+
 fn generate_frame<F: FnOnce(&mut Ui)>(ui: &mut Ui, f: F) -> Response {
     Frame::default()
         .stroke(Stroke::new(1.0, Color32::from_black_alpha(50)))
@@ -177,8 +448,17 @@ fn generate_binop_box(ui: &mut Ui, op: &mut BinaryOperation, id: u64) -> Respons
             ui.selectable_value(op, BinaryOperation::Sub, BinaryOperation::Sub.to_string());
             ui.selectable_value(op, BinaryOperation::Multiply, BinaryOperation::Multiply.to_string());
             ui.selectable_value(op, BinaryOperation::Divide, BinaryOperation::Divide.to_string());
+            ui.selectable_value(op, BinaryOperation::Modulo, BinaryOperation::Modulo.to_string());
+            ui.selectable_value(op, BinaryOperation::IntegerDivide, BinaryOperation::IntegerDivide.to_string());
             ui.selectable_value(op, BinaryOperation::Power, BinaryOperation::Power.to_string());
             ui.selectable_value(op, BinaryOperation::Root, BinaryOperation::Root.to_string());
+            ui.selectable_value(op, BinaryOperation::Log, BinaryOperation::Log.to_string());
+            ui.selectable_value(op, BinaryOperation::Beta, BinaryOperation::Beta.to_string());
+            ui.selectable_value(op, BinaryOperation::LinearSolve, BinaryOperation::LinearSolve.to_string());
+            ui.selectable_value(op, BinaryOperation::Dot, BinaryOperation::Dot.to_string());
+            ui.selectable_value(op, BinaryOperation::Cross, BinaryOperation::Cross.to_string());
+            ui.selectable_value(op, BinaryOperation::Angle, BinaryOperation::Angle.to_string());
+            ui.selectable_value(op, BinaryOperation::Project, BinaryOperation::Project.to_string());
             ui.selectable_value(op, BinaryOperation::Store, BinaryOperation::Store.to_string());
             ui.selectable_value(op, BinaryOperation::Equal, BinaryOperation::Equal.to_string());
             ui.selectable_value(op, BinaryOperation::GreaterThan, BinaryOperation::GreaterThan.to_string());
@@ -207,6 +487,32 @@ fn generate_unop_box(ui: &mut Ui, op: &mut UnaryOperation, id: u64) -> Response
             ui.selectable_value(op, UnaryOperation::InverseHyperbolicSin, UnaryOperation::InverseHyperbolicSin.to_string());
             ui.selectable_value(op, UnaryOperation::InverseHyperbolicCos, UnaryOperation::InverseHyperbolicCos.to_string());
             ui.selectable_value(op, UnaryOperation::InverseHyperbolicTan, UnaryOperation::InverseHyperbolicTan.to_string());
+            ui.selectable_value(op, UnaryOperation::NaturalLog, UnaryOperation::NaturalLog.to_string());
+            ui.selectable_value(op, UnaryOperation::Log10, UnaryOperation::Log10.to_string());
+            ui.selectable_value(op, UnaryOperation::Exp, UnaryOperation::Exp.to_string());
+            ui.selectable_value(op, UnaryOperation::SquareRoot, UnaryOperation::SquareRoot.to_string());
+            ui.selectable_value(op, UnaryOperation::Abs, UnaryOperation::Abs.to_string());
+            ui.selectable_value(op, UnaryOperation::Round, UnaryOperation::Round.to_string());
+            ui.selectable_value(op, UnaryOperation::Floor, UnaryOperation::Floor.to_string());
+            ui.selectable_value(op, UnaryOperation::Ceil, UnaryOperation::Ceil.to_string());
+            ui.selectable_value(op, UnaryOperation::Sign, UnaryOperation::Sign.to_string());
+            ui.selectable_value(op, UnaryOperation::Fractional, UnaryOperation::Fractional.to_string());
+            ui.selectable_value(op, UnaryOperation::Factorial, UnaryOperation::Factorial.to_string());
+            ui.selectable_value(op, UnaryOperation::Gamma, UnaryOperation::Gamma.to_string());
+            ui.selectable_value(op, UnaryOperation::LogGamma, UnaryOperation::LogGamma.to_string());
+            ui.selectable_value(op, UnaryOperation::Digamma, UnaryOperation::Digamma.to_string());
+            ui.selectable_value(op, UnaryOperation::Transpose, UnaryOperation::Transpose.to_string());
+            ui.selectable_value(op, UnaryOperation::Determinant, UnaryOperation::Determinant.to_string());
+            ui.selectable_value(op, UnaryOperation::Inverse, UnaryOperation::Inverse.to_string());
+            ui.selectable_value(op, UnaryOperation::Rank, UnaryOperation::Rank.to_string());
+            ui.selectable_value(op, UnaryOperation::Trace, UnaryOperation::Trace.to_string());
+            ui.selectable_value(op, UnaryOperation::LowerUpper, UnaryOperation::LowerUpper.to_string());
+            ui.selectable_value(op, UnaryOperation::QR, UnaryOperation::QR.to_string());
+            ui.selectable_value(op, UnaryOperation::Eigen, UnaryOperation::Eigen.to_string());
+            ui.selectable_value(op, UnaryOperation::SingularValues, UnaryOperation::SingularValues.to_string());
+            ui.selectable_value(op, UnaryOperation::MatrixExp, UnaryOperation::MatrixExp.to_string());
+            ui.selectable_value(op, UnaryOperation::Norm, UnaryOperation::Norm.to_string());
+            ui.selectable_value(op, UnaryOperation::Unit, UnaryOperation::Unit.to_string());
         })
         .response
 }
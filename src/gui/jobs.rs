@@ -0,0 +1,184 @@
+use crate::math::context::Interrupt;
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, SendError, Sender};
+use std::sync::Arc;
+use std::thread;
+
+/// The fewest threads evaluating expressions, so one slow expression does not
+/// hold up every other one on a machine with a single core.
+const MIN_WORKERS: usize = 2;
+/// The most threads evaluating expressions at once, however many cores there are.
+const MAX_WORKERS: usize = 8;
+
+/// How far a job has come, shared between the worker running it and the
+/// expression waiting for it.
+#[derive(Debug, Default)]
+pub struct Progress {
+    /// Cancels the job, and counts the steps its evaluations take.
+    pub interrupt: Arc<Interrupt>,
+    started: AtomicBool,
+    /// For jobs made of many evaluations, such as the points of a graph,
+    /// how many are finished out of how many there are.
+    pub done: AtomicUsize,
+    pub total: AtomicUsize,
+}
+
+impl Progress {
+    /// Whether a worker has picked the job up, rather than it still waiting in the queue.
+    pub fn started(&self) -> bool {
+        self.started.load(Ordering::Relaxed)
+    }
+
+    /// The finished part of a job with a known size, between 0 and 1.
+    pub fn fraction(&self) -> Option<f32> {
+        match self.total.load(Ordering::Relaxed) {
+            0 => None,
+            total => Some(self.done.load(Ordering::Relaxed) as f32 / total as f32),
+        }
+    }
+}
+
+struct Job {
+    progress: Arc<Progress>,
+    task: Box<dyn FnOnce() + Send>,
+    /// Reports why the task could not run to the end, in place of its result.
+    fail: Box<dyn FnOnce(String) + Send>,
+}
+
+impl Job {
+    fn run(self) {
+        self.progress.started.store(true, Ordering::Relaxed);
+        if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(self.task)) {
+            let reason = match panic.downcast_ref::<&str>() {
+                Some(reason) => reason.to_string(),
+                None => panic.downcast_ref::<String>().cloned().unwrap_or_default(),
+            };
+            (self.fail)(format!("evaluation stopped unexpectedly: {}", reason));
+        }
+    }
+}
+
+/// A fixed set of threads running evaluations in the order they were submitted.
+pub struct WorkerPool {
+    sender: Sender<Job>,
+    receiver: Arc<Mutex<Receiver<Job>>>,
+    stack_size: usize,
+    workers: usize,
+    /// How many workers are running, so the ones that could not be started
+    /// or have exited are started again.
+    running: Arc<AtomicUsize>,
+}
+
+impl WorkerPool {
+    /// Starts the workers, each with a stack of `stack_size` bytes.
+    ///
+    /// Dropping the pool lets the workers finish what is already queued and exit.
+    pub fn new(stack_size: usize) -> WorkerPool {
+        let (sender, receiver) = mpsc::channel();
+        let pool = WorkerPool {
+            sender,
+            receiver: Arc::new(Mutex::new(receiver)),
+            stack_size,
+            workers: thread::available_parallelism().map_or(MIN_WORKERS, |count| count.get().clamp(MIN_WORKERS, MAX_WORKERS)),
+            running: Arc::new(AtomicUsize::new(0)),
+        };
+        pool.start_workers();
+        pool
+    }
+
+    pub fn stack_size(&self) -> usize {
+        self.stack_size
+    }
+
+    /// Starts workers until there are as many as the pool should have,
+    /// and returns how many are running.
+    fn start_workers(&self) -> usize {
+        while self.running.load(Ordering::Relaxed) < self.workers {
+            let receiver = self.receiver.clone();
+            let running = RunningWorker(self.running.clone());
+            running.0.fetch_add(1, Ordering::Relaxed);
+            let spawned = thread::Builder::new()
+                .name("evaluation".to_string())
+                .stack_size(self.stack_size)
+                .spawn(move || {
+                    let _running = running;
+                    work(&receiver);
+                });
+            if let Err(err) = spawned {
+                eprintln!("unable to start an evaluation worker: {}", err);
+                break;
+            }
+        }
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Queues `task`, which is skipped if `progress` is cancelled before a worker gets to it.
+    /// If no worker can run it, or it panics, `fail` is called with the reason instead.
+    pub fn submit(
+        &self,
+        progress: Arc<Progress>,
+        task: impl FnOnce() + Send + 'static,
+        fail: impl FnOnce(String) + Send + 'static,
+    ) {
+        if self.start_workers() == 0 {
+            fail("no evaluation workers are running".to_string());
+            return;
+        }
+        let job = Job {
+            progress,
+            task: Box::new(task),
+            fail: Box::new(fail),
+        };
+        if let Err(SendError(job)) = self.sender.send(job) {
+            (job.fail)("no evaluation workers are running".to_string());
+        }
+    }
+}
+
+/// Counts a worker as running until its thread exits, however it exits.
+struct RunningWorker(Arc<AtomicUsize>);
+
+impl Drop for RunningWorker {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        let job = receiver.lock().recv();
+        let Ok(job) = job else {
+            return;
+        };
+        if job.progress.interrupt.is_cancelled() {
+            continue;
+        }
+        job.run();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn panicking_task_fails_its_job_and_keeps_workers_running() {
+        let pool = WorkerPool::new(1 << 20);
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..MAX_WORKERS + 1 {
+            let failed = sender.clone();
+            pool.submit(Arc::default(), || panic!("broken task"), move |err| failed.send(err).unwrap());
+        }
+        for _ in 0..MAX_WORKERS + 1 {
+            let err = receiver.recv_timeout(Duration::from_secs(10)).unwrap();
+            assert_eq!(err, "evaluation stopped unexpectedly: broken task");
+        }
+
+        let finished = sender.clone();
+        pool.submit(Arc::default(), move || finished.send("done".to_string()).unwrap(), |_| {});
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(10)).unwrap(), "done");
+    }
+}
@@ -1,4 +1,6 @@
 pub mod app;
+pub mod dependencies;
 pub mod graph;
-pub mod idx;
+pub mod jobs;
 mod top;
+pub mod worksheet;
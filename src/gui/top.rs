@@ -1,6 +1,8 @@
+use crate::gui::jobs::Progress;
 use crate::math::expr::Expression;
 use crate::math::values::Value;
 use eframe::epaint::Hsva;
+use std::sync::Arc;
 
 /// Represents an expression on a sidebar with lots of metadata.
 /// The metadata is used to cache expression information and to compute it
@@ -8,8 +10,10 @@ use eframe::epaint::Hsva;
 pub struct TopLevelExpression {
     /// The main expression associated with this top level expression.
     pub expression: Expression,
-    /// The last known hash of the Expression. Used to prevent having to compute
-    /// equality of a potentially really deep expression.
+    /// The last known hash of the Expression, combined with the hashes of the
+    /// expressions it depends on. Used to prevent having to compute equality of
+    /// a potentially really deep expression, and to notice when anything it
+    /// depends on has changed.
     pub expression_hash: u64,
     /// Represents the last known answer to the solution of the
     /// provided expression. This is not used by the graphing system.
@@ -20,4 +24,51 @@ pub struct TopLevelExpression {
     /// The last known size of the graph UI boundaries.
     /// The elements are as such: minimum X, maximum X, minimum Y, maximum Y, complex axis input
     pub graph_data_cache: (f64, f64, f64, f64, f64),
+    /// Why the expression can not be computed, shown in place of its answer.
+    pub problem: Option<String>,
+    /// The value the expression stores into its variable, handed to
+    /// the expressions that read it.
+    pub definition: Option<Value>,
+    /// Whether the answer, or for a graph the variables it is drawn with,
+    /// belongs to the current `expression_hash`.
+    pub up_to_date: bool,
+    /// Counts the jobs started for this expression. A job only stores its
+    /// results while no newer one has been started since.
+    pub generation: u64,
+    /// The job currently computing the answer or the graph, if any.
+    pub job: Option<Arc<Progress>>,
+}
+
+impl TopLevelExpression {
+    pub fn new(expression: Expression) -> TopLevelExpression {
+        TopLevelExpression {
+            expression,
+            expression_hash: u64::MAX,
+            answer_cached: None,
+            graph_cache: vec![],
+            graph_data_cache: (0.0, 0.0, 0.0, 0.0, 0.0),
+            problem: None,
+            definition: None,
+            up_to_date: false,
+            generation: 0,
+            job: None,
+        }
+    }
+
+    /// Cancels the current job, if any, and returns the generation and the
+    /// progress of a new one.
+    pub fn start_job(&mut self) -> (u64, Arc<Progress>) {
+        self.cancel_job();
+        let progress = Arc::new(Progress::default());
+        self.job = Some(progress.clone());
+        (self.generation, progress)
+    }
+
+    /// Stops the current job and makes sure nothing it has computed is stored.
+    pub fn cancel_job(&mut self) {
+        if let Some(job) = self.job.take() {
+            job.interrupt.cancel();
+        }
+        self.generation += 1;
+    }
 }
@@ -0,0 +1,160 @@
+use crate::gui::app::CalculatorApp;
+use crate::gui::top::TopLevelExpression;
+use crate::math::context::Limits;
+use crate::math::expr::Expression;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The schema version written into every saved worksheet.
+/// Bump this whenever a change to the format needs `Worksheet::migrate`
+/// to rewrite older files; adding new `Expression` variants does not.
+pub const WORKSHEET_VERSION: u32 = 2;
+
+/// A snapshot of everything the user has typed into the calculator,
+/// stored on disk as JSON.
+#[derive(Serialize, Deserialize)]
+pub struct Worksheet {
+    pub version: u32,
+    pub expressions: Vec<Expression>,
+    #[serde(default)]
+    pub complex_axis_input: f64,
+    /// Minimum X, maximum X, minimum Y, maximum Y of the graph.
+    #[serde(default)]
+    pub plot_bounds: Option<(f64, f64, f64, f64)>,
+    #[serde(default)]
+    pub limits: Limits,
+}
+
+impl Worksheet {
+    pub fn load(path: &Path) -> Result<Worksheet, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("unable to read `{}`: {}", path.display(), err))?;
+        Worksheet::from_json(&text)
+            .map_err(|err| format!("`{}` is not a valid worksheet: {}", path.display(), err))
+    }
+
+    /// Reads a worksheet, first bringing one written by an older version up to the current schema.
+    pub fn from_json(text: &str) -> Result<Worksheet, String> {
+        let mut json: serde_json::Value = serde_json::from_str(text).map_err(|err| err.to_string())?;
+        let version = json
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| "missing version".to_string())?;
+        migrate(&mut json, version)?;
+        json["version"] = WORKSHEET_VERSION.into();
+        serde_json::from_value(json).map_err(|err| err.to_string())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|err| format!("unable to serialize worksheet: {}", err))?;
+        fs::write(path, text)
+            .map_err(|err| format!("unable to write `{}`: {}", path.display(), err))
+    }
+}
+
+/// Rewrites the JSON of a worksheet saved as `version` into the current schema.
+fn migrate(json: &mut serde_json::Value, version: u64) -> Result<(), String> {
+    if version > WORKSHEET_VERSION as u64 {
+        return Err(format!(
+            "worksheet version {} is newer than the supported version {}",
+            version, WORKSHEET_VERSION
+        ));
+    }
+    if version == 0 {
+        return Err(format!("unknown worksheet version {}", version));
+    }
+    if version < 2 {
+        lambda_variables(json);
+    }
+    Ok(())
+}
+
+/// Version 1 gave every lambda a single `variable`, where lambdas now take a list of `variables`.
+fn lambda_variables(json: &mut serde_json::Value) {
+    match json {
+        serde_json::Value::Object(object) => {
+            if let Some(serde_json::Value::Object(lambda)) = object.get_mut("Lambda") {
+                if let Some(variable) = lambda.remove("variable") {
+                    lambda.insert("variables".to_string(), serde_json::Value::Array(vec![variable]));
+                }
+            }
+            object.values_mut().for_each(lambda_variables);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(lambda_variables),
+        _ => {}
+    }
+}
+
+impl CalculatorApp {
+    pub fn to_worksheet(&self) -> Worksheet {
+        Worksheet {
+            version: WORKSHEET_VERSION,
+            expressions: self
+                .exprs
+                .iter()
+                .map(|expr| expr.lock().expression.clone())
+                .collect(),
+            complex_axis_input: self.complex_axis_input,
+            plot_bounds: Some(self.plot_bounds),
+            limits: self.limits,
+        }
+    }
+
+    pub fn load_worksheet(&mut self, worksheet: Worksheet) {
+        for expr in &self.exprs {
+            expr.lock().cancel_job();
+        }
+        self.exprs = worksheet
+            .expressions
+            .into_iter()
+            .map(|expression| Arc::new(Mutex::new(TopLevelExpression::new(expression))))
+            .collect();
+        self.complex_axis_input = worksheet.complex_axis_input;
+        self.restore_bounds = worksheet.plot_bounds;
+        self.limits = worksheet.limits;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::context::Context;
+
+    #[test]
+    fn loads_version_1_lambdas() {
+        let worksheet = Worksheet::from_json(include_str!("../../tests/fixtures/worksheet_v1.json")).unwrap();
+        assert_eq!(worksheet.version, WORKSHEET_VERSION);
+        assert_eq!(worksheet.plot_bounds, Some((-10.0, 10.0, -5.0, 5.0)));
+        let mut ctx = Context::default();
+        let results: Vec<String> = worksheet.expressions.iter().map(|expr| expr.eval(&mut ctx).to_string()).collect();
+        assert_eq!(results[1], "42+0i");
+    }
+
+    #[test]
+    fn round_trips_the_current_version() {
+        let worksheet = Worksheet {
+            version: WORKSHEET_VERSION,
+            expressions: vec![Expression::parse("f = (fn(x, y) = x * y)").unwrap()],
+            complex_axis_input: 1.5,
+            plot_bounds: None,
+            limits: Limits::default(),
+        };
+        let text = serde_json::to_string(&worksheet).unwrap();
+        let loaded = Worksheet::from_json(&text).unwrap();
+        assert_eq!(loaded.expressions[0].to_string(), "f = (fn(x, y) = x * y)");
+        assert_eq!(loaded.complex_axis_input, 1.5);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let text = format!("{{\"version\": {}, \"expressions\": []}}", WORKSHEET_VERSION + 1);
+        let Err(err) = Worksheet::from_json(&text) else {
+            panic!("a newer worksheet loaded");
+        };
+        assert!(err.contains("newer than the supported version"));
+    }
+}
@@ -0,0 +1,6 @@
+#![allow(unused)]
+
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod math;
+pub mod rules;
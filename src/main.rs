@@ -1,19 +1,39 @@
 #![allow(unused)]
 
-use crate::gui::app::CalculatorApp;
+#[cfg(feature = "gui")]
+use comp_rs::gui::app::CalculatorApp;
+use comp_rs::math::context::Limits;
+#[cfg(feature = "gui")]
 use eframe::{run_native, NativeOptions};
+use std::process::ExitCode;
 
-mod gui;
-mod math;
-mod rules;
+mod cli;
+#[cfg(feature = "repl")]
+mod repl;
 
-fn main() {
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "--eval") {
+        return Limits::default().run(|| cli::eval(&args[1..]));
+    }
+    #[cfg(feature = "repl")]
+    if args.first().is_some_and(|arg| arg == "--repl") {
+        return Limits::default().run(repl::run);
+    }
+    open_window()
+}
+
+#[cfg(feature = "gui")]
+fn open_window() -> ExitCode {
     let options = NativeOptions::default();
-    let app = CalculatorApp {
-        exprs: vec![],
-        complex_axis_input: 0.0,
-        expressions_cached: false
-    };
+    let app = CalculatorApp::default();
     run_native("Calculator", options, Box::new(|_cc| Ok(Box::new(app))))
         .expect("failed to open window");
+    ExitCode::SUCCESS
 }
+
+#[cfg(not(feature = "gui"))]
+fn open_window() -> ExitCode {
+    eprintln!("comp-rs was built without the `gui` feature, use --eval or --repl");
+    ExitCode::FAILURE
+}
\ No newline at end of file
@@ -1,28 +1,136 @@
+use crate::math::expr::Expression;
 use crate::math::values::Value;
 use num::complex::Complex64;
 use std::collections::HashMap;
-use std::sync::{LazyLock, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
 
 pub static GLOBAL_MATH_CONTEXT: LazyLock<Mutex<Context>> = LazyLock::new(Mutex::default);
 
 pub struct GlobalContext;
 impl GlobalContext {
     pub fn set_variable(name: String, value: Value) {
-        GLOBAL_MATH_CONTEXT.lock().unwrap().set_variable(name, value);
+        GLOBAL_MATH_CONTEXT.lock().set_variable(name, value);
     }
 
     pub fn resolve_variable(name: &String) -> Option<Value> {
-        GLOBAL_MATH_CONTEXT.lock().unwrap().resolve_variable(name).clone()
+        GLOBAL_MATH_CONTEXT.lock().resolve_variable(name).clone()
     }
 }
 
 
+#[derive(Clone)]
 pub struct Context {
-    pub(crate) frames: Vec<Frame>
+    pub frames: Vec<Frame>,
+    pub limits: Limits,
+    /// How many function calls are currently being evaluated.
+    depth: usize,
+    /// How many expressions are currently being evaluated inside each other.
+    nesting: usize,
+    /// How many expressions have been evaluated so far.
+    steps: u64,
+    interrupt: Option<Arc<Interrupt>>,
 }
 
+/// How many steps pass between reports of progress to an [`Interrupt`].
+const PROGRESS_INTERVAL: u64 = 1024;
+
+/// Lets another thread stop an evaluation early and follow how far it has come.
+#[derive(Debug, Default)]
+pub struct Interrupt {
+    cancelled: AtomicBool,
+    steps: AtomicU64,
+}
+
+impl Interrupt {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Roughly how many steps every evaluation watched by this has taken together.
+    pub fn steps(&self) -> u64 {
+        self.steps.load(Ordering::Relaxed)
+    }
+}
+
+/// Bounds on how much work a single evaluation may do, so that runaway
+/// recursion ends in an error instead of hanging or overflowing the stack.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// The deepest functions may call each other.
+    pub max_depth: usize,
+    /// The most expressions one evaluation may evaluate.
+    pub max_steps: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_depth: 200,
+            max_steps: 10_000_000,
+        }
+    }
+}
+
+/// How deeply expressions may nest outside of any function call.
+const BASE_NESTING: usize = 500;
+
+/// How much deeper expressions may nest for every level of calls allowed,
+/// which leaves room for the bodies of ordinary functions.
+const NESTING_PER_CALL: usize = 16;
+
+/// The stack one nested expression takes in the recursive evaluator, with
+/// room to spare. Unoptimized builds use several times as much.
+const STACK_PER_NESTING: usize = if cfg!(debug_assertions) { 8 << 10 } else { 2 << 10 };
+
+impl Limits {
+    /// The deepest call depth that can be set, which keeps the stack of each
+    /// evaluation worker to a few hundred MB.
+    pub const MAX_DEPTH: usize = 5_000;
+
+    /// The call depth in effect, which no worksheet can raise past [`Limits::MAX_DEPTH`].
+    pub fn depth(&self) -> usize {
+        self.max_depth.min(Limits::MAX_DEPTH)
+    }
+
+    /// How deeply expressions may nest while they are evaluated, counting
+    /// through every function call.
+    pub fn nesting(&self) -> usize {
+        BASE_NESTING + self.depth() * NESTING_PER_CALL
+    }
+
+    /// The stack a thread needs to evaluate within these limits, as every
+    /// nested expression takes several frames of the recursive evaluator.
+    pub fn stack_size(&self) -> usize {
+        (4 << 20) + self.nesting() * STACK_PER_NESTING
+    }
+
+    /// Runs `task` on a thread with enough stack to evaluate within these limits.
+    pub fn run<T: Send>(&self, task: impl FnOnce() -> T + Send) -> T {
+        std::thread::scope(|scope| {
+            let thread = std::thread::Builder::new()
+                .name("evaluation".to_string())
+                .stack_size(self.stack_size())
+                .spawn_scoped(scope, task)
+                .expect("failed to spawn evaluation thread");
+            thread
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        })
+    }
+}
+
+#[derive(Clone)]
 pub struct Frame {
-    pub(crate) variables: HashMap<String, Value>,
+    pub variables: HashMap<String, Value>,
 }
 
 impl Default for Context {
@@ -38,10 +146,88 @@ impl Context {
         Context {
             frames: vec![Frame {
                 variables: HashMap::new()
-            }]
+            }],
+            limits: Limits::default(),
+            depth: 0,
+            nesting: 0,
+            steps: 0,
+            interrupt: None,
+        }
+    }
+
+    pub fn with_limits(limits: Limits) -> Self {
+        Context {
+            limits,
+            ..Context::default()
         }
     }
 
+    /// Reports the progress of every evaluation in this context to `interrupt`,
+    /// and stops them once it is cancelled.
+    pub fn set_interrupt(&mut self, interrupt: Arc<Interrupt>) {
+        self.interrupt = Some(interrupt);
+    }
+
+    /// Starts a fresh step budget, for when one context evaluates several
+    /// unrelated expressions in turn.
+    pub fn reset_steps(&mut self) {
+        self.steps = 0;
+    }
+
+    /// Counts one more evaluated expression against the step limit.
+    pub fn step(&mut self) -> Result<(), Value> {
+        self.steps += 1;
+        if self.steps > self.limits.max_steps {
+            return Err(Value::Error(format!(
+                "evaluation took more than {} steps",
+                self.limits.max_steps
+            )));
+        }
+        if let Some(interrupt) = &self.interrupt {
+            if self.steps.is_multiple_of(PROGRESS_INTERVAL) {
+                interrupt.steps.fetch_add(PROGRESS_INTERVAL, Ordering::Relaxed);
+            }
+            if interrupt.is_cancelled() {
+                return Err(Value::Error("evaluation was cancelled".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Enters a function call, failing when calls are nested too deeply.
+    /// Every successful call must be followed by [`Context::leave_call`].
+    pub fn enter_call(&mut self) -> Result<(), Value> {
+        if self.depth >= self.limits.depth() {
+            return Err(Value::Error(format!(
+                "functions called each other more than {} levels deep",
+                self.limits.depth()
+            )));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn leave_call(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Enters a nested expression, failing when expressions are nested too
+    /// deeply. Every successful entry must be followed by [`Context::leave_nesting`].
+    pub fn enter_nesting(&mut self) -> Result<(), Value> {
+        if self.nesting >= self.limits.nesting() {
+            return Err(Value::Error(format!(
+                "expressions nested more than {} levels deep",
+                self.limits.nesting()
+            )));
+        }
+        self.nesting += 1;
+        Ok(())
+    }
+
+    pub fn leave_nesting(&mut self) {
+        self.nesting -= 1;
+    }
+
     pub fn push_frame(&mut self) {
         self.frames.push(Frame {
             variables: HashMap::new(),
@@ -58,11 +244,60 @@ impl Context {
 
     pub fn resolve_variable(&self, name: &String) -> Option<Value> {
         for frame in self.frames.iter().rev() {
-            match frame.variables.get(name) {
-                Some(value) => return Some(value.clone()),
-                None => ()
+            if let Some(value) = frame.variables.get(name) {
+                return Some(value.clone());
             }
         }
         None
     }
+
+    /// Copies the current values of the variables `expr` reads, leaving out
+    /// `parameters`, so it can be evaluated later in another context.
+    pub fn capture(&self, expr: &Expression, parameters: &[String]) -> HashMap<String, Value> {
+        let mut captured = HashMap::new();
+        for frame in self.frames.iter().rev() {
+            for (name, value) in &frame.variables {
+                if !captured.contains_key(name) && !parameters.contains(name) && expr.depends_on(name) {
+                    captured.insert(name.clone(), value.clone());
+                }
+            }
+        }
+        captured
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_is_capped() {
+        let limits = Limits {
+            max_depth: 100_000,
+            ..Limits::default()
+        };
+        assert_eq!(limits.depth(), Limits::MAX_DEPTH);
+        assert_eq!(limits.stack_size(), Limits { max_depth: Limits::MAX_DEPTH, ..limits }.stack_size());
+    }
+
+    #[test]
+    fn deeply_nested_bodies_end_in_an_error() {
+        let limits = Limits {
+            max_depth: Limits::MAX_DEPTH,
+            ..Limits::default()
+        };
+        let body = format!("{}f(n - 1){}", "0 + (".repeat(200), ")".repeat(200));
+        let result = limits.run(|| {
+            let mut ctx = Context::with_limits(limits);
+            for line in [format!("f(n) = if(n < 1, 0, {})", body), "f(4990)".to_string()] {
+                let result = Expression::parse(&line).unwrap().eval(&mut ctx);
+                ctx.reset_steps();
+                if result.has_error() {
+                    return result.to_string();
+                }
+            }
+            String::new()
+        });
+        assert_eq!(result, format!("error: \"expressions nested more than {} levels deep\"", limits.nesting()));
+    }
 }
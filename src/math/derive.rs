@@ -0,0 +1,776 @@
+use crate::math::context::{Context, GlobalContext};
+use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
+use crate::math::idx::new_id;
+use crate::math::values::Value;
+use std::collections::BTreeSet;
+
+/// How deep calls to stored functions are inlined before giving up,
+/// so recursive functions do not expand forever.
+const MAX_INLINE_DEPTH: usize = 32;
+
+impl Expression {
+    /// Differentiates the expression with respect to `variable`.
+    ///
+    /// Every other name is treated as a constant. Calls can only be differentiated
+    /// when the function is written inline, see [`Expression::evaluate_derivative`]
+    /// for how stored functions are handled.
+    pub fn derive(&self, variable: &str) -> Result<Expression, String> {
+        // A lambda is differentiated as a function, even with respect to its own parameter.
+        if let Expression::Lambda { variables, expr } = self {
+            return Ok(Expression::Lambda {
+                variables: variables.clone(),
+                expr: Box::new(expr.derive(variable)?),
+            });
+        }
+        if !self.depends_on(variable) {
+            return Ok(Expression::number(0.0));
+        }
+
+        match self {
+            Expression::Literal { .. } => Ok(Expression::number(1.0)),
+            Expression::Parenthesis { expr, .. } => expr.derive(variable),
+            Expression::GraphExpression { expr } => Ok(Expression::GraphExpression {
+                expr: Box::new(expr.derive(variable)?),
+            }),
+            Expression::Vector { .. } => derive_entry(self, variable),
+            Expression::Unary { operation, expr, .. } => derive_unary(operation, expr, variable),
+            Expression::Binary { op, lhs, rhs, .. } => derive_binary(*op, lhs, rhs, variable),
+            Expression::Summation {
+                minimum,
+                maximum,
+                variable: index,
+                expression,
+            } => {
+                if minimum.depends_on(variable) || maximum.depends_on(variable) {
+                    return Err(format!("can not differentiate a sum whose bounds depend on `{}`", variable));
+                }
+                Ok(Expression::Summation {
+                    minimum: minimum.clone(),
+                    maximum: maximum.clone(),
+                    variable: index.clone(),
+                    expression: Box::new(expression.derive(variable)?),
+                })
+            }
+            Expression::Product {
+                minimum,
+                maximum,
+                variable: index,
+                expression,
+            } => {
+                if minimum.depends_on(variable) || maximum.depends_on(variable) {
+                    return Err(format!("can not differentiate a product whose bounds depend on `{}`", variable));
+                }
+                // (f1 * f2 * ...)' = (f1 * f2 * ...) * (f1' / f1 + f2' / f2 + ...)
+                let terms = Expression::Summation {
+                    minimum: minimum.clone(),
+                    maximum: maximum.clone(),
+                    variable: index.clone(),
+                    expression: Box::new(div(expression.derive(variable)?, *expression.clone())),
+                };
+                Ok(mul(self.clone(), terms))
+            }
+            Expression::Integral {
+                lower,
+                upper,
+                variable: index,
+                integrand,
+            } => {
+                // Leibniz rule, the bounds are substituted into the integrand by calling it.
+                let at = |bound: &Expression| {
+                    Expression::binary(
+                        BinaryOperation::Invoke,
+                        Expression::Lambda {
+                            variables: vec![*index.clone()],
+                            expr: integrand.clone(),
+                        },
+                        bound.clone(),
+                    )
+                };
+                let mut derivative = sub(
+                    mul(at(upper), upper.derive(variable)?),
+                    mul(at(lower), lower.derive(variable)?),
+                );
+                if literal_name(index)? != variable && integrand.depends_on(variable) {
+                    derivative = add(
+                        derivative,
+                        Expression::Integral {
+                            lower: lower.clone(),
+                            upper: upper.clone(),
+                            variable: index.clone(),
+                            integrand: Box::new(integrand.derive(variable)?),
+                        },
+                    );
+                }
+                Ok(derivative)
+            }
+            Expression::Solve { .. } | Expression::Roots { .. } => {
+                Err("can not differentiate the roots of an equation".to_string())
+            }
+            // Piecewise, the derivative is that of whichever branch applies.
+            Expression::Conditional {
+                condition,
+                consequent,
+                alternative,
+            } => Ok(Expression::Conditional {
+                condition: condition.clone(),
+                consequent: Box::new(consequent.derive(variable)?),
+                alternative: Box::new(alternative.derive(variable)?),
+            }),
+            Expression::Derivative {
+                variable: inner,
+                expression,
+            } => expression.derive(literal_name(inner)?)?.derive(variable),
+            Expression::Lambda { .. } => unreachable!(),
+        }
+    }
+
+    /// Evaluates the derivative of `expression` with respect to `variable`.
+    ///
+    /// Calls to functions stored in variables are replaced with the functions
+    /// themselves first, so `f(x)` can be differentiated after `f = fn(x) = x^2`.
+    pub fn evaluate_derivative(variable: &Expression, expression: &Expression, ctx: &mut Context) -> Value {
+        let name = match literal_name(variable) {
+            Ok(name) => name,
+            Err(err) => return Value::Error(err),
+        };
+        let mut expression = expression.clone();
+        expression.inline_functions(ctx, 0);
+        match expression.derive(name) {
+            Ok(derivative) => derivative.eval(ctx),
+            Err(err) => Value::Error(err),
+        }
+    }
+
+    /// Whether `variable` occurs in the expression without being bound by it.
+    pub fn depends_on(&self, variable: &str) -> bool {
+        match self {
+            Expression::Literal { content, .. } => content == variable,
+            Expression::Unary { expr, .. }
+            | Expression::Parenthesis { expr, .. }
+            | Expression::GraphExpression { expr } => expr.depends_on(variable),
+            Expression::Binary { lhs, rhs, .. } => lhs.depends_on(variable) || rhs.depends_on(variable),
+            Expression::Vector { exprs, .. } => exprs.iter().any(|expr| expr.depends_on(variable)),
+            Expression::Summation {
+                minimum,
+                maximum,
+                variable: index,
+                expression,
+            }
+            | Expression::Product {
+                minimum,
+                maximum,
+                variable: index,
+                expression,
+            }
+            | Expression::Solve {
+                minimum,
+                maximum,
+                variable: index,
+                equation: expression,
+            }
+            | Expression::Integral {
+                lower: minimum,
+                upper: maximum,
+                variable: index,
+                integrand: expression,
+            } => {
+                minimum.depends_on(variable)
+                    || maximum.depends_on(variable)
+                    || (literal_name(index) != Ok(variable) && expression.depends_on(variable))
+            }
+            Expression::Derivative { expression, .. } => expression.depends_on(variable),
+            Expression::Roots {
+                polynomial,
+                variable: index,
+            } => literal_name(index) != Ok(variable) && polynomial.depends_on(variable),
+            Expression::Conditional {
+                condition,
+                consequent,
+                alternative,
+            } => condition.depends_on(variable) || consequent.depends_on(variable) || alternative.depends_on(variable),
+            Expression::Lambda { variables, expr } => {
+                variables.iter().all(|parameter| literal_name(parameter) != Ok(variable)) && expr.depends_on(variable)
+            }
+        }
+    }
+
+    /// Every name the expression reads without binding it itself.
+    pub fn free_variables(&self) -> BTreeSet<String> {
+        let mut names = BTreeSet::new();
+        self.collect_names(&mut names);
+        names.retain(|name| self.depends_on(name));
+        names
+    }
+
+    fn collect_names(&self, names: &mut BTreeSet<String>) {
+        match self {
+            Expression::Literal { content, .. } => {
+                if !content.is_empty() && content.parse::<f64>().is_err() {
+                    names.insert(content.clone());
+                }
+            }
+            Expression::Unary { expr, .. }
+            | Expression::Parenthesis { expr, .. }
+            | Expression::GraphExpression { expr }
+            | Expression::Derivative { expression: expr, .. }
+            | Expression::Roots { polynomial: expr, .. }
+            | Expression::Lambda { expr, .. } => expr.collect_names(names),
+            Expression::Binary { lhs, rhs, .. } => {
+                lhs.collect_names(names);
+                rhs.collect_names(names);
+            }
+            Expression::Vector { exprs, .. } => {
+                for expr in exprs {
+                    expr.collect_names(names);
+                }
+            }
+            Expression::Summation {
+                minimum,
+                maximum,
+                expression,
+                ..
+            }
+            | Expression::Product {
+                minimum,
+                maximum,
+                expression,
+                ..
+            }
+            | Expression::Solve {
+                minimum,
+                maximum,
+                equation: expression,
+                ..
+            }
+            | Expression::Integral {
+                lower: minimum,
+                upper: maximum,
+                integrand: expression,
+                ..
+            }
+            | Expression::Conditional {
+                condition: minimum,
+                consequent: maximum,
+                alternative: expression,
+            } => {
+                minimum.collect_names(names);
+                maximum.collect_names(names);
+                expression.collect_names(names);
+            }
+        }
+    }
+
+    /// Replaces calls to functions stored in variables with the functions themselves.
+    fn inline_functions(&mut self, ctx: &Context, depth: usize) {
+        let mut function_depth = depth;
+        if let Expression::Binary {
+            op: BinaryOperation::Invoke,
+            lhs,
+            ..
+        } = self
+        {
+            if let Expression::Literal { content, .. } = &**lhs {
+                let function = if content.starts_with("@") {
+                    GlobalContext::resolve_variable(content)
+                } else {
+                    ctx.resolve_variable(content)
+                };
+                if let Some(function @ Value::Lambda(_)) = function {
+                    if let Some(function) = value_expression(&function).filter(|_| depth < MAX_INLINE_DEPTH) {
+                        **lhs = function;
+                        function_depth += 1;
+                    }
+                }
+            }
+        }
+
+        match self {
+            Expression::Unary { expr, .. }
+            | Expression::Parenthesis { expr, .. }
+            | Expression::GraphExpression { expr } => expr.inline_functions(ctx, depth),
+            Expression::Binary { lhs, rhs, .. } => {
+                lhs.inline_functions(ctx, function_depth);
+                rhs.inline_functions(ctx, depth);
+            }
+            Expression::Vector { exprs, .. } => {
+                for expr in exprs {
+                    expr.inline_functions(ctx, depth);
+                }
+            }
+            Expression::Summation {
+                minimum,
+                maximum,
+                expression,
+                ..
+            }
+            | Expression::Product {
+                minimum,
+                maximum,
+                expression,
+                ..
+            }
+            | Expression::Solve {
+                minimum,
+                maximum,
+                equation: expression,
+                ..
+            }
+            | Expression::Integral {
+                lower: minimum,
+                upper: maximum,
+                integrand: expression,
+                ..
+            }
+            | Expression::Conditional {
+                condition: minimum,
+                consequent: maximum,
+                alternative: expression,
+            } => {
+                minimum.inline_functions(ctx, depth);
+                maximum.inline_functions(ctx, depth);
+                expression.inline_functions(ctx, depth);
+            }
+            Expression::Derivative { expression, .. } => expression.inline_functions(ctx, depth),
+            Expression::Roots { polynomial, .. } => polynomial.inline_functions(ctx, depth),
+            Expression::Lambda { expr, .. } => expr.inline_functions(ctx, depth),
+            Expression::Literal { .. } => {}
+        }
+    }
+}
+
+/// Differentiates a vector entry by entry, keeping the rows of a matrix as
+/// vectors even where they are constant so the result has the same shape.
+fn derive_entry(expr: &Expression, variable: &str) -> Result<Expression, String> {
+    match expr {
+        Expression::Vector { exprs, id } => Ok(Expression::Vector {
+            exprs: exprs
+                .iter()
+                .map(|expr| derive_entry(expr, variable))
+                .collect::<Result<_, _>>()?,
+            id: *id,
+        }),
+        _ => expr.derive(variable),
+    }
+}
+
+fn derive_unary(operation: &UnaryOperation, u: &Expression, variable: &str) -> Result<Expression, String> {
+    let du = u.derive(variable)?;
+    let u = u.clone();
+    let outer = match operation {
+        UnaryOperation::Negate => return Ok(neg(du)),
+        UnaryOperation::Fractional => return Ok(du),
+        // Piecewise constant, so the derivative is zero wherever it exists.
+        UnaryOperation::Round | UnaryOperation::Floor | UnaryOperation::Ceil | UnaryOperation::Sign => {
+            return Ok(Expression::number(0.0))
+        }
+        UnaryOperation::Sin => Expression::unary(UnaryOperation::Cos, u),
+        UnaryOperation::Cos => neg(Expression::unary(UnaryOperation::Sin, u)),
+        UnaryOperation::Tan => div(number(1.0), pow(Expression::unary(UnaryOperation::Cos, u), number(2.0))),
+        UnaryOperation::InverseSin => div(number(1.0), sqrt(sub(number(1.0), pow(u, number(2.0))))),
+        UnaryOperation::InverseCos => neg(div(number(1.0), sqrt(sub(number(1.0), pow(u, number(2.0)))))),
+        UnaryOperation::InverseTan => div(number(1.0), add(number(1.0), pow(u, number(2.0)))),
+        UnaryOperation::HyperbolicSin => Expression::unary(UnaryOperation::HyperbolicCos, u),
+        UnaryOperation::HyperbolicCos => Expression::unary(UnaryOperation::HyperbolicSin, u),
+        UnaryOperation::HyperbolicTan => div(
+            number(1.0),
+            pow(Expression::unary(UnaryOperation::HyperbolicCos, u), number(2.0)),
+        ),
+        UnaryOperation::InverseHyperbolicSin => div(number(1.0), sqrt(add(pow(u, number(2.0)), number(1.0)))),
+        UnaryOperation::InverseHyperbolicCos => div(number(1.0), sqrt(sub(pow(u, number(2.0)), number(1.0)))),
+        UnaryOperation::InverseHyperbolicTan => div(number(1.0), sub(number(1.0), pow(u, number(2.0)))),
+        UnaryOperation::NaturalLog => div(number(1.0), u),
+        UnaryOperation::Log10 => div(number(1.0), mul(u, ln(number(10.0)))),
+        UnaryOperation::Exp => Expression::unary(UnaryOperation::Exp, u),
+        UnaryOperation::SquareRoot => div(number(1.0), mul(number(2.0), sqrt(u))),
+        UnaryOperation::Abs => Expression::unary(UnaryOperation::Sign, u),
+        UnaryOperation::Factorial => mul(
+            Expression::unary(UnaryOperation::Factorial, u.clone()),
+            Expression::unary(UnaryOperation::Digamma, add(u, number(1.0))),
+        ),
+        UnaryOperation::Gamma => mul(
+            Expression::unary(UnaryOperation::Gamma, u.clone()),
+            Expression::unary(UnaryOperation::Digamma, u),
+        ),
+        UnaryOperation::LogGamma => Expression::unary(UnaryOperation::Digamma, u),
+        UnaryOperation::Digamma => return Err("can not differentiate digamma".to_string()),
+        // Matrix functions are not scalar, so the chain rule takes its matrix form.
+        UnaryOperation::Transpose | UnaryOperation::Trace => return Ok(Expression::unary(operation.clone(), du)),
+        UnaryOperation::Rank => return Ok(number(0.0)),
+        // Jacobi's formula, d det(A) = det(A) * trace(A^-1 * dA).
+        UnaryOperation::Determinant => {
+            let inverse = Expression::unary(UnaryOperation::Inverse, u.clone());
+            return Ok(mul(
+                Expression::unary(UnaryOperation::Determinant, u),
+                Expression::unary(UnaryOperation::Trace, mul(inverse, du)),
+            ));
+        }
+        UnaryOperation::Inverse => {
+            let inverse = Expression::unary(UnaryOperation::Inverse, u);
+            return Ok(neg(mul(mul(inverse.clone(), du), inverse)));
+        }
+        UnaryOperation::LowerUpper
+        | UnaryOperation::QR
+        | UnaryOperation::Eigen
+        | UnaryOperation::SingularValues
+        | UnaryOperation::MatrixExp => {
+            return Err(format!("can not differentiate {}", operation))
+        }
+        // The length changes by the part of the change along the vector, dot(u, du) / norm(u).
+        UnaryOperation::Norm if is(&du, 0.0) => return Ok(number(0.0)),
+        UnaryOperation::Norm => {
+            let dot = Expression::binary(BinaryOperation::Dot, u.clone(), du);
+            return Ok(div(dot, Expression::unary(UnaryOperation::Norm, u)));
+        }
+        UnaryOperation::Unit => return div(u.clone(), Expression::unary(UnaryOperation::Norm, u)).derive(variable),
+    };
+    Ok(mul(outer, du))
+}
+
+fn derive_binary(
+    op: BinaryOperation,
+    u: &Expression,
+    v: &Expression,
+    variable: &str,
+) -> Result<Expression, String> {
+    match op {
+        BinaryOperation::Add => Ok(add(u.derive(variable)?, v.derive(variable)?)),
+        BinaryOperation::Sub => Ok(sub(u.derive(variable)?, v.derive(variable)?)),
+        BinaryOperation::Multiply => Ok(add(
+            mul(u.derive(variable)?, v.clone()),
+            mul(u.clone(), v.derive(variable)?),
+        )),
+        BinaryOperation::Divide if !v.depends_on(variable) => Ok(div(u.derive(variable)?, v.clone())),
+        BinaryOperation::Divide => Ok(div(
+            sub(mul(u.derive(variable)?, v.clone()), mul(u.clone(), v.derive(variable)?)),
+            pow(v.clone(), number(2.0)),
+        )),
+        BinaryOperation::Power => derive_power(u, v, variable),
+        // The degree of a root comes first, `n √ x` is `x^(1 / n)`.
+        BinaryOperation::Root => pow(v.clone(), div(number(1.0), u.clone())).derive(variable),
+        // The base of a logarithm comes first, `log_b(x)` is `ln(x) / ln(b)`.
+        BinaryOperation::Log => div(ln(v.clone()), ln(u.clone())).derive(variable),
+        BinaryOperation::Modulo => sub(
+            u.clone(),
+            mul(v.clone(), Expression::unary(UnaryOperation::Floor, div(u.clone(), v.clone()))),
+        )
+        .derive(variable),
+        BinaryOperation::IntegerDivide => Ok(number(0.0)),
+        BinaryOperation::Beta => {
+            // d/da B(a, b) = B(a, b) * (ψ(a) - ψ(a + b)), and likewise for b.
+            let digamma_sum = Expression::unary(UnaryOperation::Digamma, add(u.clone(), v.clone()));
+            let du = mul(
+                sub(Expression::unary(UnaryOperation::Digamma, u.clone()), digamma_sum.clone()),
+                u.derive(variable)?,
+            );
+            let dv = mul(
+                sub(Expression::unary(UnaryOperation::Digamma, v.clone()), digamma_sum),
+                v.derive(variable)?,
+            );
+            Ok(mul(
+                Expression::binary(BinaryOperation::Beta, u.clone(), v.clone()),
+                add(du, dv),
+            ))
+        }
+        // From A * x = b, A * dx = db - dA * x.
+        BinaryOperation::LinearSolve => {
+            let solution = Expression::binary(BinaryOperation::LinearSolve, u.clone(), v.clone());
+            let rhs = sub(v.derive(variable)?, mul(u.derive(variable)?, solution));
+            if is(&rhs, 0.0) {
+                return Ok(number(0.0));
+            }
+            Ok(Expression::binary(BinaryOperation::LinearSolve, u.clone(), rhs))
+        }
+        BinaryOperation::Dot | BinaryOperation::Cross => derive_product(op, u, v, variable),
+        // The angle is acos(dot(u, v) / (norm(u) * norm(v))).
+        BinaryOperation::Angle => {
+            let lengths = mul(
+                Expression::unary(UnaryOperation::Norm, u.clone()),
+                Expression::unary(UnaryOperation::Norm, v.clone()),
+            );
+            let cosine = div(Expression::binary(BinaryOperation::Dot, u.clone(), v.clone()), lengths);
+            Expression::unary(UnaryOperation::InverseCos, cosine).derive(variable)
+        }
+        // The projection is dot(v, u) / dot(v, v) * v.
+        BinaryOperation::Project => {
+            let factor = div(
+                Expression::binary(BinaryOperation::Dot, v.clone(), u.clone()),
+                Expression::binary(BinaryOperation::Dot, v.clone(), v.clone()),
+            );
+            mul(factor, v.clone()).derive(variable)
+        }
+        BinaryOperation::Invoke => derive_call(u, v, variable),
+        BinaryOperation::Store => Err("can not differentiate an assignment".to_string()),
+        BinaryOperation::GreaterThan
+        | BinaryOperation::LessThan
+        | BinaryOperation::GreaterThanOrEqual
+        | BinaryOperation::LessThanOrEqual
+        | BinaryOperation::Equal => Err("can not differentiate a comparison".to_string()),
+    }
+}
+
+/// The product rule for products of two vectors, leaving out the sides that do not change.
+fn derive_product(op: BinaryOperation, u: &Expression, v: &Expression, variable: &str) -> Result<Expression, String> {
+    let (du, dv) = (u.derive(variable)?, v.derive(variable)?);
+    Ok(match (is(&du, 0.0), is(&dv, 0.0)) {
+        (true, true) => number(0.0),
+        (true, false) => Expression::binary(op, u.clone(), dv),
+        (false, true) => Expression::binary(op, du, v.clone()),
+        (false, false) => add(Expression::binary(op, du, v.clone()), Expression::binary(op, u.clone(), dv)),
+    })
+}
+
+fn derive_power(u: &Expression, v: &Expression, variable: &str) -> Result<Expression, String> {
+    if !v.depends_on(variable) {
+        // (u^n)' = n * u^(n - 1) * u'
+        let exponent = match as_number(v) {
+            Some(n) => number(n - 1.0),
+            None => sub(v.clone(), number(1.0)),
+        };
+        return Ok(mul(mul(v.clone(), pow(u.clone(), exponent)), u.derive(variable)?));
+    }
+    let power = pow(u.clone(), v.clone());
+    if !u.depends_on(variable) {
+        // (a^v)' = a^v * ln(a) * v'
+        return Ok(mul(mul(power, ln(u.clone())), v.derive(variable)?));
+    }
+    // (u^v)' = u^v * (v' * ln(u) + v * u' / u)
+    Ok(mul(
+        power,
+        add(
+            mul(v.derive(variable)?, ln(u.clone())),
+            div(mul(v.clone(), u.derive(variable)?), u.clone()),
+        ),
+    ))
+}
+
+/// Applies the chain rule to a call of an inline function,
+/// `f(u, v)' = f_x(u, v) * u' + f_y(u, v) * v'`, adding the function's own
+/// derivative when its body mentions `variable` directly.
+fn derive_call(function: &Expression, argument: &Expression, variable: &str) -> Result<Expression, String> {
+    // `f(u, v)` is stored as `f(u)(v)`.
+    let mut lambda = function;
+    let mut arguments = vec![argument];
+    loop {
+        match lambda {
+            Expression::Parenthesis { expr, .. } => lambda = expr,
+            Expression::Binary {
+                op: BinaryOperation::Invoke,
+                lhs,
+                rhs,
+                ..
+            } => {
+                arguments.push(rhs);
+                lambda = lhs;
+            }
+            _ => break,
+        }
+    }
+    arguments.reverse();
+    let Expression::Lambda { variables, expr: body } = lambda else {
+        return Err(format!("can not differentiate a call to `{}`", function));
+    };
+    if variables.len() != arguments.len() {
+        return Err(format!(
+            "can not differentiate a call to `{}` with {} arguments",
+            lambda,
+            arguments.len()
+        ));
+    }
+    let call = |body: Expression| {
+        let lambda = Expression::Lambda {
+            variables: variables.clone(),
+            expr: Box::new(body),
+        };
+        arguments.iter().fold(lambda, |call, argument| {
+            Expression::binary(BinaryOperation::Invoke, call, (*argument).clone())
+        })
+    };
+
+    let mut derivative = Expression::number(0.0);
+    for (parameter, argument) in variables.iter().zip(&arguments) {
+        // Captured values are passed as leading arguments, and may be functions themselves.
+        if !argument.depends_on(variable) {
+            continue;
+        }
+        let partial = call(body.derive(literal_name(parameter)?)?);
+        derivative = add(derivative, mul(partial, argument.derive(variable)?));
+    }
+    if !body.depends_on(variable) || variables.iter().any(|parameter| literal_name(parameter) == Ok(variable)) {
+        return Ok(derivative);
+    }
+    Ok(add(derivative, call(body.derive(variable)?)))
+}
+
+/// Writes a value back as an expression that evaluates to it, if it has one.
+///
+/// Functions become lambdas taking their captured variables and bound
+/// arguments as extra leading parameters, which are then applied right away.
+fn value_expression(value: &Value) -> Option<Expression> {
+    match value {
+        Value::Number(value) if value.im == 0.0 => Some(Expression::number(value.re)),
+        Value::Number(value) => {
+            let imaginary = mul(Expression::number(value.im), Expression::variable("i"));
+            Some(add(Expression::number(value.re), imaginary))
+        }
+        Value::Vector(values) => Some(Expression::Vector {
+            exprs: values.iter().map(value_expression).collect::<Option<_>>()?,
+            id: new_id(),
+        }),
+        Value::Lambda(function) => {
+            let mut captured: Vec<(&String, &Value)> = function.captured.iter().collect();
+            captured.sort_by_key(|(name, _)| *name);
+            let mut applied = vec![];
+            let mut variables = vec![];
+            for (name, value) in captured {
+                variables.push(Expression::variable(name));
+                applied.push(value_expression(value)?);
+            }
+            variables.extend(function.parameters.iter().map(|name| Expression::variable(name)));
+            for argument in &function.arguments {
+                applied.push(value_expression(argument)?);
+            }
+            let lambda = Expression::Lambda {
+                variables,
+                expr: Box::new(function.body.clone()),
+            };
+            Some(applied.into_iter().fold(lambda, |call, argument| {
+                Expression::binary(BinaryOperation::Invoke, call, argument)
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn literal_name(expr: &Expression) -> Result<&str, String> {
+    match expr {
+        Expression::Literal { content, .. } => Ok(content),
+        _ => Err("variables must be a literal".to_string()),
+    }
+}
+
+fn as_number(expr: &Expression) -> Option<f64> {
+    match expr {
+        Expression::Literal { content, .. } => content.parse().ok(),
+        Expression::Unary {
+            operation: UnaryOperation::Negate,
+            expr,
+            ..
+        } => as_number(expr).map(|value| -value),
+        Expression::Parenthesis { expr, .. } => as_number(expr),
+        _ => None,
+    }
+}
+
+// The builders below fold the zeros and ones the rules produce, so
+// constant factors do not pile up in the result.
+
+fn number(value: f64) -> Expression {
+    Expression::number(value)
+}
+
+fn is(expr: &Expression, value: f64) -> bool {
+    as_number(expr) == Some(value)
+}
+
+fn add(lhs: Expression, rhs: Expression) -> Expression {
+    if is(&lhs, 0.0) {
+        return rhs;
+    }
+    if is(&rhs, 0.0) {
+        return lhs;
+    }
+    Expression::binary(BinaryOperation::Add, lhs, rhs)
+}
+
+fn sub(lhs: Expression, rhs: Expression) -> Expression {
+    if is(&rhs, 0.0) {
+        return lhs;
+    }
+    if is(&lhs, 0.0) {
+        return neg(rhs);
+    }
+    Expression::binary(BinaryOperation::Sub, lhs, rhs)
+}
+
+fn mul(lhs: Expression, rhs: Expression) -> Expression {
+    if is(&lhs, 0.0) || is(&rhs, 0.0) {
+        return number(0.0);
+    }
+    if is(&lhs, 1.0) {
+        return rhs;
+    }
+    if is(&rhs, 1.0) {
+        return lhs;
+    }
+    Expression::binary(BinaryOperation::Multiply, lhs, rhs)
+}
+
+fn div(lhs: Expression, rhs: Expression) -> Expression {
+    if is(&lhs, 0.0) {
+        return number(0.0);
+    }
+    if is(&rhs, 1.0) {
+        return lhs;
+    }
+    Expression::binary(BinaryOperation::Divide, lhs, rhs)
+}
+
+fn pow(lhs: Expression, rhs: Expression) -> Expression {
+    if is(&rhs, 1.0) {
+        return lhs;
+    }
+    Expression::binary(BinaryOperation::Power, lhs, rhs)
+}
+
+fn neg(expr: Expression) -> Expression {
+    match as_number(&expr) {
+        Some(0.0) => number(0.0),
+        Some(value) => number(-value),
+        None => Expression::unary(UnaryOperation::Negate, expr),
+    }
+}
+
+fn ln(expr: Expression) -> Expression {
+    Expression::unary(UnaryOperation::NaturalLog, expr)
+}
+
+fn sqrt(expr: Expression) -> Expression {
+    Expression::unary(UnaryOperation::SquareRoot, expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::context::Context;
+    use crate::math::expr::Expression;
+
+    fn derived(source: &str, variable: &str) -> String {
+        Expression::parse(source).unwrap().derive(variable).unwrap().to_string()
+    }
+
+    #[test]
+    fn applies_the_rules() {
+        assert_eq!(derived("x^3", "x"), "3 * x^2");
+        assert_eq!(derived("sin(x) * x", "x"), "cos(x) * x + sin(x)");
+        assert_eq!(derived("exp(2 * x)", "x"), "exp(2 * x) * 2");
+        assert_eq!(derived("2^x", "x"), "2^x * ln(2)");
+        assert_eq!(derived("x / y", "x"), "1 ÷ y");
+        assert_eq!(derived("y * x", "y"), "x");
+    }
+
+    #[test]
+    fn keeps_the_shape_of_vectors() {
+        assert_eq!(derived("[x, x^2]", "x"), "[1, 2 * x]");
+    }
+
+    #[test]
+    fn derives_calls_of_stored_functions() {
+        let mut ctx = Context::default();
+        for line in ["x = 3", "f = (fn(t) = t * t)"] {
+            Expression::parse(line).unwrap().eval(&mut ctx);
+        }
+        let evaluate = |source: &str, ctx: &mut Context| Expression::parse(source).unwrap().eval(ctx).to_string();
+        assert_eq!(evaluate("derive(x, x^2)", &mut ctx), "6+0i");
+        assert_eq!(evaluate("derive(x, f(x))", &mut ctx), "6+0i");
+        assert_eq!(evaluate("derive(x, f(2 * x))", &mut ctx), "24+0i");
+    }
+}
@@ -1,11 +1,23 @@
 use crate::math::context::{Context, GlobalContext};
 use crate::math::expr::{BinaryOperation, Expression};
-use crate::math::values::Value;
+use crate::math::values::{Function, Value};
 use crate::math::values::Value::Number;
 use num::complex::Complex64;
 
 impl Expression {
     pub fn eval(&self, ctx: &mut Context) -> Value {
+        if let Err(err) = ctx.step() {
+            return err;
+        }
+        if let Err(err) = ctx.enter_nesting() {
+            return err;
+        }
+        let value = self.evaluate(ctx);
+        ctx.leave_nesting();
+        value
+    }
+
+    fn evaluate(&self, ctx: &mut Context) -> Value {
         match self {
             Expression::Unary {
                 operation,
@@ -14,16 +26,54 @@ impl Expression {
             } => Value::unary_op(operation.clone(), &expr.eval(ctx)),
             Expression::Binary { op, lhs, rhs, id } => match op {
                 BinaryOperation::Store => {
-                    let right = rhs.eval(ctx);
-                    if let Expression::Literal { content, id, new_literal } = *lhs.clone() {
-                        if content.starts_with("@") {
-                            GlobalContext::set_variable(content.clone(), right.clone());
-                        } else {
-                            ctx.set_variable(content.clone(), right.clone());
+                    // `f(x, y) = ...` is short for `f = fn(x, y) = ...`.
+                    let (content, mut right) = match lhs.function_signature() {
+                        Some((name, variables)) => {
+                            let lambda = Expression::Lambda {
+                                variables,
+                                expr: rhs.clone(),
+                            };
+                            (name, lambda.eval(ctx))
                         }
+                        None => match &**lhs {
+                            Expression::Literal { content, .. } if content.parse::<f64>().is_err() => {
+                                (content.as_str(), rhs.eval(ctx))
+                            }
+                            _ => return Value::Error("can only assign to a variable".to_string()),
+                        },
+                    };
+                    // A stored function refers to itself by its new name, not by
+                    // whatever that name held before.
+                    if let Value::Lambda(function) = &mut right {
+                        function.captured.remove(content);
+                        function.name.get_or_insert_with(|| content.to_string());
+                    }
+                    if content.starts_with("@") {
+                        GlobalContext::set_variable(content.to_string(), right.clone());
+                    } else {
+                        ctx.set_variable(content.to_string(), right.clone());
                     }
                     right
                 }
+                BinaryOperation::Invoke => {
+                    // `f(a, b)` is stored as `f(a)(b)`, the arguments are gathered
+                    // so the call is checked against the function as a whole.
+                    let mut function = &**lhs;
+                    let mut arguments = vec![&**rhs];
+                    while let Expression::Binary {
+                        op: BinaryOperation::Invoke,
+                        lhs,
+                        rhs,
+                        ..
+                    } = function
+                    {
+                        arguments.push(rhs);
+                        function = lhs;
+                    }
+                    let function = function.eval(ctx);
+                    let arguments = arguments.iter().rev().map(|argument| argument.eval(ctx)).collect();
+                    function.call(arguments, ctx)
+                }
                 _ => Value::bin_op(*op, &lhs.eval(ctx), &rhs.eval(ctx), ctx),
             },
             Expression::Literal { content, id, new_literal } => {
@@ -31,11 +81,11 @@ impl Expression {
                     return Number(result.into());
                 }
                 if content.starts_with("@") {
-                    if let Some(result) = GlobalContext::resolve_variable(&content) {
+                    if let Some(result) = GlobalContext::resolve_variable(content) {
                         return result;
                     }
                 } else {
-                    if let Some(result) = ctx.resolve_variable(&content) {
+                    if let Some(result) = ctx.resolve_variable(content) {
                         return result;
                     }
                 }
@@ -52,15 +102,107 @@ impl Expression {
                 variable,
                 expression,
             } => Self::evaluate_summation(minimum, maximum, variable, expression, ctx),
-            Expression::Lambda { variable, expr } => {
-                let Expression::Literal { ref content, ref id, ref new_literal } = **variable else {
-                    return Value::Error("variable must be a literal".to_string());
-                };
-                Value::Lambda(
-                    content.clone(),
-                    *expr.clone()
-                )
+            Expression::Product {
+                minimum,
+                maximum,
+                variable,
+                expression,
+            } => Self::evaluate_product(minimum, maximum, variable, expression, ctx),
+            Expression::Solve {
+                minimum,
+                maximum,
+                variable,
+                equation,
+            } => Self::evaluate_solve(minimum, maximum, variable, equation, ctx),
+            Expression::Integral {
+                lower,
+                upper,
+                variable,
+                integrand,
+            } => Self::evaluate_integral(lower, upper, variable, integrand, ctx),
+            Expression::Derivative {
+                variable,
+                expression,
+            } => Self::evaluate_derivative(variable, expression, ctx),
+            Expression::Roots {
+                polynomial,
+                variable,
+            } => Self::evaluate_roots(polynomial, variable, ctx),
+            // Only the chosen branch is evaluated, so recursive functions can stop.
+            Expression::Conditional {
+                condition,
+                consequent,
+                alternative,
+            } => match condition.eval(ctx) {
+                Value::Boolean(true) => consequent.eval(ctx),
+                Value::Boolean(false) => alternative.eval(ctx),
+                Value::Error(err) => Value::Error(err),
+                value => Value::Error(format!("condition must be true or false, not {}", value)),
+            },
+            Expression::Lambda { variables, expr } => {
+                let mut parameters: Vec<String> = Vec::with_capacity(variables.len());
+                for variable in variables {
+                    let Expression::Literal { ref content, .. } = *variable else {
+                        return Value::Error("variable must be a literal".to_string());
+                    };
+                    if parameters.contains(content) {
+                        return Value::Error(format!("parameter `{}` is repeated", content));
+                    }
+                    parameters.push(content.clone());
+                }
+                Value::Lambda(Box::new(Function {
+                    captured: ctx.capture(expr, &parameters),
+                    parameters,
+                    body: *expr.clone(),
+                    arguments: vec![],
+                    name: None,
+                }))
+            }
+        }
+    }
+
+    /// Reads the left side of a definition such as `f(x, y) = x * y` as the name
+    /// of the function and its parameters.
+    pub fn function_signature(&self) -> Option<(&str, Vec<Expression>)> {
+        let mut function = self;
+        let mut variables = vec![];
+        while let Expression::Binary {
+            op: BinaryOperation::Invoke,
+            lhs,
+            rhs,
+            ..
+        } = function
+        {
+            match &**rhs {
+                Expression::Literal { content, .. } if content.parse::<f64>().is_err() => {
+                    variables.insert(0, *rhs.clone())
+                }
+                _ => return None,
             }
+            function = lhs;
+        }
+        match function {
+            Expression::Literal { content, .. } if !variables.is_empty() => Some((content, variables)),
+            _ => None,
+        }
+    }
+
+    /// The variable a definition such as `a = 3` or `f(x) = x^2` stores into.
+    pub fn defined_name(&self) -> Option<&str> {
+        let Expression::Binary {
+            op: BinaryOperation::Store,
+            lhs,
+            ..
+        } = self
+        else {
+            return None;
+        };
+        match lhs.function_signature() {
+            Some((name, _)) => Some(name),
+            None => match &**lhs {
+                Expression::Literal { content, .. } => Some(content),
+                _ => None,
+            },
         }
     }
 
@@ -70,6 +212,50 @@ impl Expression {
         variable: &Expression,
         expression: &Expression,
         ctx: &mut Context,
+    ) -> Value {
+        Self::evaluate_series(
+            "summation",
+            minimum,
+            maximum,
+            variable,
+            expression,
+            Value::Number(Complex64::new(0.0, 0.0)),
+            Value::add,
+            ctx,
+        )
+    }
+
+    pub fn evaluate_product(
+        minimum: &Expression,
+        maximum: &Expression,
+        variable: &Expression,
+        expression: &Expression,
+        ctx: &mut Context,
+    ) -> Value {
+        Self::evaluate_series(
+            "product",
+            minimum,
+            maximum,
+            variable,
+            expression,
+            Value::Number(Complex64::new(1.0, 0.0)),
+            Value::mul,
+            ctx,
+        )
+    }
+
+    /// Folds `expression` over every whole number from `minimum` to `maximum`,
+    /// binding each one to `variable` and restoring its previous value afterwards.
+    #[allow(clippy::too_many_arguments)]
+    fn evaluate_series(
+        kind: &str,
+        minimum: &Expression,
+        maximum: &Expression,
+        variable: &Expression,
+        expression: &Expression,
+        initial: Value,
+        combine: fn(&Value, &Value) -> Value,
+        ctx: &mut Context,
     ) -> Value {
         let Expression::Literal {
             content: ref variable_name,
@@ -82,33 +268,154 @@ impl Expression {
         let min_val = minimum.eval(ctx).round().clone();
         let max_val = maximum.eval(ctx).round().clone();
         let Value::Number(min_val) = min_val else {
-            return Value::Error("minimum of summation must be a number".to_string());
+            return Value::Error(format!("minimum of {} must be a number", kind));
         };
         let Value::Number(max_val) = max_val else {
-            return Value::Error("maximum of summation must be a number".to_string());
+            return Value::Error(format!("maximum of {} must be a number", kind));
         };
         if min_val.im != 0.0 {
-            return Value::Error("summation minimum can not be complex".to_string());
+            return Value::Error(format!("{} minimum can not be complex", kind));
         };
         if max_val.im != 0.0 {
-            return Value::Error("summation maximum can not be complex".to_string());
+            return Value::Error(format!("{} maximum can not be complex", kind));
         };
-        if min_val.re >= max_val.re {
-            return Value::Error("summation maximum can not be larger than minimum".to_string());
+        if min_val.re > max_val.re {
+            return Value::Error(format!("{} maximum can not be smaller than minimum", kind));
         };
-        let old_value = ctx.resolve_variable(variable_name);
-        let mut base = Value::Number(Complex64::new(0.0, 0.0));
+        // The loop variable lives in its own frame, so it never outlasts the
+        // series, while anything else the body stores is kept afterwards.
+        ctx.push_frame();
+        let mut base = initial;
         for intermediate_value in (min_val.re as i64)..=(max_val.re as i64) {
             ctx.set_variable(
                 variable_name.clone(),
                 Value::Number(Complex64::new(intermediate_value as f64, 0.0)),
             );
+            if let Err(err) = ctx.step() {
+                base = err;
+                break;
+            }
             let result = expression.eval(ctx);
-            base = Value::add(&base, &result);
+            base = combine(&base, &result);
         }
-        if let Some(old_value) = old_value {
-            ctx.set_variable(variable_name.clone(), old_value.clone());
+        let mut frame = ctx.frames.pop().unwrap();
+        frame.variables.remove(variable_name);
+        for (name, value) in frame.variables {
+            ctx.set_variable(name, value);
         }
         base
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::math::context::{Context, Limits};
+    use crate::math::expr::Expression;
+    use crate::math::values::Value;
+    use num::complex::Complex64;
+
+    fn evaluate(source: &str, ctx: &mut Context) -> String {
+        Expression::parse(source).unwrap().eval(ctx).to_string()
+    }
+
+    fn resolve(name: &str, ctx: &Context) -> Option<String> {
+        ctx.resolve_variable(&name.to_string()).map(|value| value.to_string())
+    }
+
+    fn number(re: f64) -> Value {
+        Value::Number(Complex64::new(re, 0.0))
+    }
+
+    #[test]
+    fn series_restores_its_variable() {
+        let mut ctx = Context::default();
+        ctx.set_variable("i".to_string(), number(7.0));
+        assert_eq!(evaluate("sum(i, 1, 4, i)", &mut ctx), "10+0i");
+        assert_eq!(resolve("i", &ctx).as_deref(), Some("7+0i"));
+        assert_eq!(evaluate("prod(k, 1, 4, k)", &mut ctx), "24+0i");
+        assert_eq!(resolve("k", &ctx), None);
+    }
+
+    #[test]
+    fn series_keeps_stores_from_its_body() {
+        let mut ctx = Context::default();
+        evaluate("s = 0", &mut ctx);
+        assert_eq!(evaluate("sum(i, 1, 4, s = s + i)", &mut ctx), "20+0i");
+        assert_eq!(resolve("s", &ctx).as_deref(), Some("10+0i"));
+        assert_eq!(evaluate("prod(k, 1, 3, t = k)", &mut ctx), "6+0i");
+        assert_eq!(resolve("t", &ctx).as_deref(), Some("3+0i"));
+        assert_eq!(resolve("k", &ctx), None);
+    }
+
+    #[test]
+    fn series_restores_its_variable_when_out_of_steps() {
+        let mut ctx = Context::with_limits(Limits {
+            max_steps: 50,
+            ..Limits::default()
+        });
+        ctx.set_variable("i".to_string(), number(7.0));
+        assert!(evaluate("sum(i, 1, 1000, i)", &mut ctx).starts_with("error"));
+        assert_eq!(resolve("i", &ctx).as_deref(), Some("7+0i"));
+    }
+
+    #[test]
+    fn series_ranges() {
+        let mut ctx = Context::default();
+        assert_eq!(evaluate("sum(i, 3, 3, 2 * i)", &mut ctx), "6+0i");
+        assert_eq!(evaluate("prod(i, 3, 3, i)", &mut ctx), "3+0i");
+        assert_eq!(
+            evaluate("sum(i, 3, 1, i)", &mut ctx),
+            "error: \"summation maximum can not be smaller than minimum\""
+        );
+    }
+
+    #[test]
+    fn closures_keep_their_captured_values() {
+        let mut ctx = Context::default();
+        evaluate("a = 2", &mut ctx);
+        evaluate("g = (fn(t) = t * a)", &mut ctx);
+        evaluate("a = 10", &mut ctx);
+        assert_eq!(evaluate("g(3)", &mut ctx), "6+0i");
+
+        evaluate("adder(n) = (fn(t) = t + n)", &mut ctx);
+        evaluate("add5 = adder(5)", &mut ctx);
+        evaluate("n = 100", &mut ctx);
+        assert_eq!(evaluate("add5(1)", &mut ctx), "6+0i");
+
+        // Another entry only sees the function, not the variables it read.
+        let mut other = Context::default();
+        other.set_variable("g".to_string(), ctx.resolve_variable(&"g".to_string()).unwrap());
+        assert_eq!(evaluate("g(3)", &mut other), "6+0i");
+    }
+
+    /// Evaluates `source` as its own worksheet entry, which only sees the
+    /// definitions it is given, the way the side panel shares them.
+    fn evaluate_entry(source: &str, definitions: &[(&str, &Value)]) -> Value {
+        let mut ctx = Context::default();
+        for (name, value) in definitions {
+            ctx.set_variable(name.to_string(), (*value).clone());
+        }
+        Expression::parse(source).unwrap().eval(&mut ctx)
+    }
+
+    #[test]
+    fn named_functions_are_shared_across_entries() {
+        let square = evaluate_entry("f(x) = x * x", &[]);
+        let offset = evaluate_entry("c = 3", &[]);
+        let shifted = evaluate_entry("g(x) = f(x) + c", &[("f", &square), ("c", &offset)]);
+        assert_eq!(evaluate_entry("f(4)", &[("f", &square)]).to_string(), "16+0i");
+        assert_eq!(evaluate_entry("g(2)", &[("g", &shifted)]).to_string(), "7+0i");
+        assert_eq!(
+            evaluate_entry("f(2) * g(1)", &[("f", &square), ("g", &shifted)]).to_string(),
+            "16+0i"
+        );
+    }
+
+    #[test]
+    fn named_functions_recurse() {
+        let factorial = evaluate_entry("fact(n) = if(n < 1, 1, n * fact(n - 1))", &[]);
+        assert_eq!(evaluate_entry("fact(5)", &[("fact", &factorial)]).to_string(), "120+0i");
+        let fibonacci = evaluate_entry("fib(n) = if(n < 2, n, fib(n - 1) + fib(n - 2))", &[]);
+        assert_eq!(evaluate_entry("fib(15)", &[("fib", &fibonacci)]).to_string(), "610+0i");
+    }
+}
@@ -1,35 +1,46 @@
+use crate::math::idx::new_id;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
 use std::rc::{Rc, Weak};
 
 #[derive(Clone, Debug)]
 pub struct ExprRef(Rc<RefCell<Expression>>);
 pub struct WeakExprRef(Weak<RefCell<Expression>>);
 
-#[derive(Clone, Debug, Hash)]
+#[derive(Clone, Debug, Hash, Serialize, Deserialize)]
+#[allow(clippy::enum_variant_names)]
 pub enum Expression {
     Unary {
         operation: UnaryOperation,
         expr: Box<Expression>,
+        #[serde(skip, default = "new_id")]
         id: u64,
     },
     Binary {
         op: BinaryOperation,
         lhs: Box<Expression>,
         rhs: Box<Expression>,
+        #[serde(skip, default = "new_id")]
         id: u64,
     },
     Vector {
         exprs: Vec<Expression>,
+        #[serde(skip, default = "new_id")]
         id: u64,
     },
     Literal {
         content: String,
+        #[serde(skip, default = "new_id")]
         id: u64,
+        #[serde(skip)]
         new_literal: bool
     },
     Parenthesis {
         expr: Box<Expression>,
+        #[serde(skip, default = "new_id")]
         id: u64,
+        #[serde(skip)]
         unbox_to_binop: bool
     },
     GraphExpression {
@@ -41,8 +52,39 @@ pub enum Expression {
         variable: Box<Expression>,
         expression: Box<Expression>,
     },
-    Lambda {
+    Product {
+        minimum: Box<Expression>,
+        maximum: Box<Expression>,
+        variable: Box<Expression>,
+        expression: Box<Expression>,
+    },
+    Solve {
+        minimum: Box<Expression>,
+        maximum: Box<Expression>,
+        variable: Box<Expression>,
+        equation: Box<Expression>,
+    },
+    Integral {
+        lower: Box<Expression>,
+        upper: Box<Expression>,
+        variable: Box<Expression>,
+        integrand: Box<Expression>,
+    },
+    Derivative {
+        variable: Box<Expression>,
+        expression: Box<Expression>,
+    },
+    Roots {
+        polynomial: Box<Expression>,
         variable: Box<Expression>,
+    },
+    Conditional {
+        condition: Box<Expression>,
+        consequent: Box<Expression>,
+        alternative: Box<Expression>,
+    },
+    Lambda {
+        variables: Vec<Expression>,
         expr: Box<Expression>,
     }
 }
@@ -57,7 +99,7 @@ impl Expression {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Hash)]
+#[derive(Clone, Debug, PartialEq, Hash, Serialize, Deserialize)]
 pub enum UnaryOperation {
     Negate,
     Sin,
@@ -73,11 +115,41 @@ pub enum UnaryOperation {
     InverseHyperbolicSin,
     InverseHyperbolicCos,
     InverseHyperbolicTan,
+
+    NaturalLog,
+    Log10,
+    Exp,
+    SquareRoot,
+
+    Abs,
+    Round,
+    Floor,
+    Ceil,
+    Sign,
+    Fractional,
+
+    Factorial,
+    Gamma,
+    LogGamma,
+    Digamma,
+
+    Transpose,
+    Determinant,
+    Inverse,
+    Rank,
+    Trace,
+    LowerUpper,
+    QR,
+    Eigen,
+    SingularValues,
+    MatrixExp,
+    Norm,
+    Unit,
 }
 
-impl ToString for UnaryOperation {
-    fn to_string(&self) -> String {
-        match self {
+impl Display for UnaryOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
             UnaryOperation::Negate => "-",
             UnaryOperation::Sin => "sin",
             UnaryOperation::Cos => "cos",
@@ -90,20 +162,56 @@ impl ToString for UnaryOperation {
             UnaryOperation::HyperbolicTan => "tanh",
             UnaryOperation::InverseHyperbolicSin => "sinh^-1",
             UnaryOperation::InverseHyperbolicCos => "cosh^-1",
-            UnaryOperation::InverseHyperbolicTan => "tanh^-1"
-        }
-        .to_string()
+            UnaryOperation::InverseHyperbolicTan => "tanh^-1",
+            UnaryOperation::NaturalLog => "ln",
+            UnaryOperation::Log10 => "log10",
+            UnaryOperation::Exp => "exp",
+            UnaryOperation::SquareRoot => "sqrt",
+            UnaryOperation::Abs => "abs",
+            UnaryOperation::Round => "round",
+            UnaryOperation::Floor => "floor",
+            UnaryOperation::Ceil => "ceil",
+            UnaryOperation::Sign => "sgn",
+            UnaryOperation::Fractional => "frac",
+            UnaryOperation::Factorial => "!",
+            UnaryOperation::Gamma => "gamma",
+            UnaryOperation::LogGamma => "lgamma",
+            UnaryOperation::Digamma => "digamma",
+            UnaryOperation::Transpose => "transpose",
+            UnaryOperation::Determinant => "det",
+            UnaryOperation::Inverse => "inv",
+            UnaryOperation::Rank => "rank",
+            UnaryOperation::Trace => "trace",
+            UnaryOperation::LowerUpper => "lu",
+            UnaryOperation::QR => "qr",
+            UnaryOperation::Eigen => "eig",
+            UnaryOperation::SingularValues => "svd",
+            UnaryOperation::MatrixExp => "expm",
+            UnaryOperation::Norm => "norm",
+            UnaryOperation::Unit => "unit",
+        })
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Copy, Serialize, Deserialize)]
 pub enum BinaryOperation {
     Add,
     Sub,
     Multiply,
     Divide,
+    Modulo,
+    IntegerDivide,
     Power,
     Root,
+    Log,
+    Beta,
+    /// Solves the linear system with the matrix on the left and the right side on the right.
+    LinearSolve,
+    Dot,
+    Cross,
+    Angle,
+    /// Projects the vector on the left onto the one on the right.
+    Project,
     Store,
     Invoke,
 
@@ -114,15 +222,24 @@ pub enum BinaryOperation {
     Equal
 }
 
-impl ToString for BinaryOperation {
-    fn to_string(&self) -> String {
-        match self {
+impl Display for BinaryOperation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
             BinaryOperation::Add => "+",
             BinaryOperation::Sub => "-",
             BinaryOperation::Multiply => "*",
             BinaryOperation::Divide => "÷",
+            BinaryOperation::Modulo => "mod",
+            BinaryOperation::IntegerDivide => "div",
             BinaryOperation::Power => "^",
             BinaryOperation::Root => "√",
+            BinaryOperation::Log => "log_",
+            BinaryOperation::Beta => "beta",
+            BinaryOperation::LinearSolve => "solve",
+            BinaryOperation::Dot => "dot",
+            BinaryOperation::Cross => "cross",
+            BinaryOperation::Angle => "angle",
+            BinaryOperation::Project => "proj",
             BinaryOperation::Store => "=",
             BinaryOperation::Invoke => "(",
             BinaryOperation::GreaterThan => ">",
@@ -130,7 +247,6 @@ impl ToString for BinaryOperation {
             BinaryOperation::GreaterThanOrEqual => ">=",
             BinaryOperation::LessThanOrEqual => "<=",
             BinaryOperation::Equal => "==",
-        }
-        .to_string()
+        })
     }
 }
@@ -0,0 +1,180 @@
+use crate::math::values::Value;
+use num::complex::Complex64;
+use num::Zero;
+
+/// Reads the entries of a vector of numbers.
+fn entries(value: &Value) -> Result<Vec<Complex64>, String> {
+    let Value::Vector(entries) = value else {
+        return Err(format!("{} is not a vector", value));
+    };
+    entries
+        .iter()
+        .map(|entry| match entry {
+            Value::Number(number) => Ok(*number),
+            Value::Error(err) => Err(err.clone()),
+            _ => Err("entries of a vector must be numbers".to_string()),
+        })
+        .collect()
+}
+
+/// Reads two vectors of numbers of the same length, or explains what `operation` needs.
+fn pair(lhs: &Value, rhs: &Value, operation: &str) -> Result<(Vec<Complex64>, Vec<Complex64>), String> {
+    let (lhs, rhs) = (entries(lhs)?, entries(rhs)?);
+    if lhs.len() != rhs.len() {
+        return Err(format!("vectors must have same length {}", operation));
+    }
+    Ok((lhs, rhs))
+}
+
+/// The inner product, which conjugates the left vector so that a vector's
+/// product with itself is its length squared.
+fn inner(lhs: &[Complex64], rhs: &[Complex64]) -> Complex64 {
+    lhs.iter().zip(rhs).map(|(x, y)| x.conj() * y).sum()
+}
+
+fn length(entries: &[Complex64]) -> f64 {
+    entries.iter().map(|entry| entry.norm_sqr()).sum::<f64>().sqrt()
+}
+
+fn vector(entries: impl IntoIterator<Item = Complex64>) -> Value {
+    Value::Vector(entries.into_iter().map(Value::Number).collect())
+}
+
+impl Value {
+    pub fn dot(lhs: &Value, rhs: &Value) -> Value {
+        pair(lhs, rhs, "for a dot product").map_or_else(Value::Error, |(lhs, rhs)| Value::Number(inner(&lhs, &rhs)))
+    }
+
+    pub fn cross(lhs: &Value, rhs: &Value) -> Value {
+        let (lhs, rhs) = match pair(lhs, rhs, "for a cross product") {
+            Ok(pair) => pair,
+            Err(err) => return Value::Error(err),
+        };
+        if lhs.len() != 3 {
+            return Value::Error("vectors must have 3 entries for a cross product".to_string());
+        }
+        vector([
+            lhs[1] * rhs[2] - lhs[2] * rhs[1],
+            lhs[2] * rhs[0] - lhs[0] * rhs[2],
+            lhs[0] * rhs[1] - lhs[1] * rhs[0],
+        ])
+    }
+
+    /// The euclidean length of a vector, which for a matrix is the square root
+    /// of the sum of its entries' squared magnitudes.
+    pub fn norm(&self) -> Value {
+        match self {
+            Value::Number(number) => Value::Number(Complex64::new(number.norm(), 0.0)),
+            Value::Vector(values) => {
+                let mut sum = 0.0;
+                for value in values {
+                    match value.norm() {
+                        Value::Number(norm) => sum += norm.re * norm.re,
+                        other => return other,
+                    }
+                }
+                Value::Number(Complex64::new(sum.sqrt(), 0.0))
+            }
+            Value::Error(_) => self.clone(),
+            _ => Value::Error("invalid operand".to_string()),
+        }
+    }
+
+    /// The vector of length 1 pointing the same way.
+    pub fn unit(&self) -> Value {
+        match self.norm() {
+            Value::Number(norm) if norm.is_zero() => Value::Error("the zero vector has no direction".to_string()),
+            Value::Number(norm) => Value::div(self, &Value::Number(norm)),
+            other => other,
+        }
+    }
+
+    /// The angle between two vectors, in radians.
+    pub fn angle(lhs: &Value, rhs: &Value) -> Value {
+        let (lhs, rhs) = match pair(lhs, rhs, "to find the angle between them") {
+            Ok(pair) => pair,
+            Err(err) => return Value::Error(err),
+        };
+        let (lhs_length, rhs_length) = (length(&lhs), length(&rhs));
+        if lhs_length == 0.0 || rhs_length == 0.0 {
+            return Value::Error("the zero vector has no direction".to_string());
+        }
+        // Scaled to the same length, the two vectors make a rhombus whose diagonals
+        // give the angle accurately even where its cosine is close to 1.
+        let scaled = |entries: &[Complex64], factor: f64| -> Vec<Complex64> { entries.iter().map(|entry| entry * factor).collect() };
+        let (lhs, rhs) = (scaled(&lhs, rhs_length), scaled(&rhs, lhs_length));
+        let difference: Vec<Complex64> = lhs.iter().zip(&rhs).map(|(x, y)| x - y).collect();
+        let sum: Vec<Complex64> = lhs.iter().zip(&rhs).map(|(x, y)| x + y).collect();
+        Value::Number(Complex64::new(2.0 * length(&difference).atan2(length(&sum)), 0.0))
+    }
+
+    /// Projects the left vector onto the line along the right one.
+    pub fn project(lhs: &Value, rhs: &Value) -> Value {
+        let (lhs, rhs) = match pair(lhs, rhs, "to be projected") {
+            Ok(pair) => pair,
+            Err(err) => return Value::Error(err),
+        };
+        let squared = inner(&rhs, &rhs);
+        if squared.is_zero() {
+            return Value::Error("can not project onto the zero vector".to_string());
+        }
+        let factor = inner(&rhs, &lhs) / squared;
+        vector(rhs.into_iter().map(|entry| factor * entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::context::Context;
+    use crate::math::expr::Expression;
+
+    fn evaluate(source: &str) -> String {
+        Expression::parse(source).unwrap().eval(&mut Context::default()).to_string()
+    }
+
+    #[test]
+    fn products_of_vectors() {
+        assert_eq!(evaluate("dot([1, 2, 3], [4, 5, 6])"), "32+0i");
+        assert_eq!(evaluate("dot([i, 0], [i, 0])"), "1+0i");
+        assert_eq!(evaluate("cross([1, 0, 0], [0, 1, 0])"), "<0+0i,  0+0i,  1+0i,  >");
+        assert_eq!(evaluate("cross([1, 2, 3], [1, 2, 3])"), "<0+0i,  0+0i,  0+0i,  >");
+    }
+
+    #[test]
+    fn lengths_and_directions() {
+        assert_eq!(evaluate("norm([3, 4])"), "5+0i");
+        assert_eq!(evaluate("norm([[1, 2], [2, 4]])"), "5+0i");
+        assert_eq!(evaluate("norm(-2)"), "2+0i");
+        assert_eq!(evaluate("unit([3, 4])"), "<0.6+0i,  0.8+0i,  >");
+        assert_eq!(evaluate("angle([1, 0], [0, 2])"), format!("{}+0i", std::f64::consts::FRAC_PI_2));
+        assert_eq!(evaluate("angle([1, 1], [2, 2])"), "0+0i");
+        assert_eq!(evaluate("proj([2, 3], [1, 0])"), "<2+0i,  0+0i,  >");
+        assert_eq!(evaluate("proj([1, 1], [2, 2])"), "<1+0i,  1+0i,  >");
+    }
+
+    #[test]
+    fn numbers_broadcast_over_vectors() {
+        assert_eq!(evaluate("2 * [1, 2]"), "<2+0i,  4+0i,  >");
+        assert_eq!(evaluate("[1, 2] * 3"), "<3+0i,  6+0i,  >");
+        assert_eq!(evaluate("[1, 2] / 2"), "<0.5+0i,  1+0i,  >");
+        assert_eq!(evaluate("2 / [1, 4]"), "<2+0i,  0.5+0i,  >");
+        assert_eq!(evaluate("2 * [[1, 0], [0, 1]]"), "<<2+0i,  0+0i,  >,  <0+0i,  2+0i,  >,  >");
+    }
+
+    #[test]
+    fn mismatched_vectors_are_errors() {
+        for (source, message) in [
+            ("dot([1, 2], [1, 2, 3])", "vectors must have same length for a dot product"),
+            ("cross([1, 2, 3], [1, 2])", "vectors must have same length for a cross product"),
+            ("cross([1, 2], [3, 4])", "vectors must have 3 entries for a cross product"),
+            ("angle([1], [1, 2])", "vectors must have same length to find the angle between them"),
+            ("proj([1, 2], [1])", "vectors must have same length to be projected"),
+            ("dot(1, [1])", "1+0i is not a vector"),
+            ("unit([0, 0])", "the zero vector has no direction"),
+            ("angle([0, 0], [1, 0])", "the zero vector has no direction"),
+            ("proj([1, 2], [0, 0])", "can not project onto the zero vector"),
+        ] {
+            assert_eq!(evaluate(source), format!("error: \"{}\"", message), "evaluating {}", source);
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use crate::math::context::Context;
+use crate::math::expr::Expression;
+use crate::math::values::Value;
+use num::complex::Complex64;
+
+/// Nodes of the 15 point Kronrod rule on [-1, 1], from the outside in. Every
+/// other node, starting from the second, is also a node of the 7 point Gauss rule.
+const KRONROD_NODES: [f64; 8] = [
+    0.991_455_371_120_812_6,
+    0.949_107_912_342_758_5,
+    0.864_864_423_359_769_1,
+    0.741_531_185_599_394_4,
+    0.586_087_235_467_691_1,
+    0.405_845_151_377_397_2,
+    0.207_784_955_007_898_5,
+    0.0,
+];
+const KRONROD_WEIGHTS: [f64; 8] = [
+    0.022_935_322_010_529_22,
+    0.063_092_092_629_978_55,
+    0.104_790_010_322_250_2,
+    0.140_653_259_715_525_9,
+    0.169_004_726_639_267_9,
+    0.190_350_578_064_785_4,
+    0.204_432_940_075_298_9,
+    0.209_482_141_084_727_8,
+];
+const GAUSS_WEIGHTS: [f64; 4] = [
+    0.129_484_966_168_869_7,
+    0.279_705_391_489_276_7,
+    0.381_830_050_505_118_9,
+    0.417_959_183_673_469_4,
+];
+
+const ABSOLUTE_TOLERANCE: f64 = 1e-10;
+const RELATIVE_TOLERANCE: f64 = 1e-10;
+/// How many times the worst interval is split in half before giving up.
+const MAX_SUBDIVISIONS: usize = 1000;
+
+impl Expression {
+    /// Integrates `integrand` over `variable` from `lower` to `upper` with adaptive
+    /// Gauss–Kronrod quadrature, always splitting the interval with the largest
+    /// error estimate until the total estimate is small enough.
+    ///
+    /// Infinite bounds such as `inf` are mapped onto a finite interval first.
+    pub fn evaluate_integral(
+        lower: &Expression,
+        upper: &Expression,
+        variable: &Expression,
+        integrand: &Expression,
+        ctx: &mut Context,
+    ) -> Value {
+        let Expression::Literal {
+            content: ref variable_name,
+            ..
+        } = variable
+        else {
+            return Value::Error("variables must be a literal".to_string());
+        };
+        let Value::Number(lower_val) = lower.eval(ctx) else {
+            return Value::Error("lower bound of integral must be a number".to_string());
+        };
+        let Value::Number(upper_val) = upper.eval(ctx) else {
+            return Value::Error("upper bound of integral must be a number".to_string());
+        };
+        // Negating an infinite bound leaves a NaN imaginary part behind.
+        let is_complex = |bound: Complex64| bound.re.is_finite() && bound.im != 0.0;
+        if is_complex(lower_val) || is_complex(upper_val) {
+            return Value::Error("integral bounds can not be complex".to_string());
+        }
+        let (lower_val, upper_val) = (lower_val.re, upper_val.re);
+        if lower_val.is_nan() || upper_val.is_nan() {
+            return Value::Error("integral bounds must be numbers".to_string());
+        }
+        if lower_val == upper_val {
+            return Value::Number(Complex64::new(0.0, 0.0));
+        }
+
+        let (from, to, sign) = if lower_val < upper_val {
+            (lower_val, upper_val, 1.0)
+        } else {
+            (upper_val, lower_val, -1.0)
+        };
+        let substitution = match (from.is_finite(), to.is_finite()) {
+            (true, true) => Substitution::None,
+            (true, false) => Substitution::FromLower(from),
+            (false, true) => Substitution::ToUpper(to),
+            (false, false) => Substitution::Both,
+        };
+
+        ctx.push_frame();
+        let mut integrator = Integrator {
+            variable: variable_name,
+            integrand,
+            ctx,
+            substitution,
+        };
+        let (a, b) = substitution.interval(from, to);
+        let result = integrator.integrate(a, b);
+        ctx.pop_frame();
+        match result {
+            Ok(value) => Value::Number(value * sign),
+            Err(error) => error,
+        }
+    }
+}
+
+/// Maps an infinite range onto a finite interval of `t`.
+#[derive(Copy, Clone)]
+enum Substitution {
+    None,
+    /// `x = a + t / (1 - t)` for `t` in [0, 1)
+    FromLower(f64),
+    /// `x = b - (1 - t) / t` for `t` in (0, 1]
+    ToUpper(f64),
+    /// `x = t / (1 - t^2)` for `t` in (-1, 1)
+    Both,
+}
+
+impl Substitution {
+    fn interval(&self, from: f64, to: f64) -> (f64, f64) {
+        match self {
+            Substitution::None => (from, to),
+            Substitution::FromLower(_) | Substitution::ToUpper(_) => (0.0, 1.0),
+            Substitution::Both => (-1.0, 1.0),
+        }
+    }
+
+    /// Returns `x` at `t` along with `dx/dt`.
+    fn apply(&self, t: f64) -> (f64, f64) {
+        match self {
+            Substitution::None => (t, 1.0),
+            Substitution::FromLower(a) => (a + t / (1.0 - t), 1.0 / ((1.0 - t) * (1.0 - t))),
+            Substitution::ToUpper(b) => (b - (1.0 - t) / t, 1.0 / (t * t)),
+            Substitution::Both => {
+                let denominator = 1.0 - t * t;
+                (t / denominator, (1.0 + t * t) / (denominator * denominator))
+            }
+        }
+    }
+}
+
+struct Integrator<'a> {
+    variable: &'a String,
+    integrand: &'a Expression,
+    ctx: &'a mut Context,
+    substitution: Substitution,
+}
+
+/// An interval of the substituted variable with its Kronrod estimate.
+struct Segment {
+    a: f64,
+    b: f64,
+    value: Complex64,
+    error: f64,
+}
+
+impl Integrator<'_> {
+    fn evaluate(&mut self, t: f64) -> Result<Complex64, Value> {
+        let (x, jacobian) = self.substitution.apply(t);
+        // Nodes next to an infinite bound can round onto it, where the
+        // substituted integrand vanishes.
+        if !x.is_finite() {
+            return Ok(Complex64::new(0.0, 0.0));
+        }
+        self.ctx.set_variable(self.variable.clone(), Value::Number(Complex64::new(x, 0.0)));
+        match self.integrand.eval(self.ctx) {
+            // Far out on an infinite range the integrand may vanish faster than
+            // the jacobian blows up, which would otherwise be `0 * inf`.
+            Value::Number(value) if value == Complex64::new(0.0, 0.0) => Ok(value),
+            Value::Number(value) if (value * jacobian).is_finite() => Ok(value * jacobian),
+            Value::Number(_) => Err(Value::Error(format!("integrand is not finite at {} = {:e}", self.variable, x))),
+            Value::Error(err) => Err(Value::Error(err)),
+            _ => Err(Value::Error("integrand must be a number".to_string())),
+        }
+    }
+
+    /// Applies the 15 point Kronrod rule to [a, b], estimating the error
+    /// from its difference to the embedded 7 point Gauss rule.
+    fn kronrod(&mut self, a: f64, b: f64) -> Result<Segment, Value> {
+        let center = (a + b) / 2.0;
+        let half_length = (b - a) / 2.0;
+
+        let center_value = self.evaluate(center)?;
+        let mut kronrod = center_value * KRONROD_WEIGHTS[7];
+        let mut gauss = center_value * GAUSS_WEIGHTS[3];
+        for (index, node) in KRONROD_NODES[..7].iter().enumerate() {
+            let offset = half_length * node;
+            let pair = self.evaluate(center - offset)? + self.evaluate(center + offset)?;
+            kronrod += pair * KRONROD_WEIGHTS[index];
+            if index % 2 == 1 {
+                gauss += pair * GAUSS_WEIGHTS[index / 2];
+            }
+        }
+
+        Ok(Segment {
+            a,
+            b,
+            value: kronrod * half_length,
+            error: ((kronrod - gauss) * half_length).norm(),
+        })
+    }
+
+    fn integrate(&mut self, a: f64, b: f64) -> Result<Complex64, Value> {
+        let mut segments = vec![self.kronrod(a, b)?];
+        for _ in 0..MAX_SUBDIVISIONS {
+            let value: Complex64 = segments.iter().map(|segment| segment.value).sum();
+            let error: f64 = segments.iter().map(|segment| segment.error).sum();
+            if error <= ABSOLUTE_TOLERANCE.max(RELATIVE_TOLERANCE * value.norm()) {
+                return Ok(value);
+            }
+
+            let (worst, _) = segments
+                .iter()
+                .enumerate()
+                .max_by(|(_, lhs), (_, rhs)| lhs.error.total_cmp(&rhs.error))
+                .unwrap();
+            let segment = segments.swap_remove(worst);
+            let middle = (segment.a + segment.b) / 2.0;
+            segments.push(self.kronrod(segment.a, middle)?);
+            segments.push(self.kronrod(middle, segment.b)?);
+        }
+
+        let value: Complex64 = segments.iter().map(|segment| segment.value).sum();
+        let error: f64 = segments.iter().map(|segment| segment.error).sum();
+        Err(Value::Error(format!(
+            "integral did not converge, reached {} with an estimated error of {:e}",
+            value, error
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::context::Context;
+    use crate::math::expr::Expression;
+    use crate::math::values::Value;
+
+    fn integrate(source: &str) -> Value {
+        Expression::parse(source).unwrap().eval(&mut Context::default())
+    }
+
+    fn assert_close(value: Value, expected: f64) {
+        let Value::Number(number) = value else {
+            panic!("expected a number, got {}", value);
+        };
+        assert!((number.re - expected).abs() < 1e-9 && number.im.abs() < 1e-9, "{} is not {}", number, expected);
+    }
+
+    #[test]
+    fn integrates_over_finite_ranges() {
+        assert_close(integrate("int(x, 0, 1, x^2)"), 1.0 / 3.0);
+        assert_close(integrate("int(x, 1, 0, x)"), -0.5);
+        assert_close(integrate("int(x, 1, 1, x)"), 0.0);
+    }
+
+    #[test]
+    fn integrates_over_infinite_ranges() {
+        assert_close(integrate("int(x, 0, inf, exp(-x))"), 1.0);
+        assert_close(integrate("int(x, -inf, inf, exp(-(x^2)))"), std::f64::consts::PI.sqrt());
+    }
+
+    #[test]
+    fn reports_integrands_that_can_not_be_evaluated() {
+        assert_eq!(integrate("int(x, 0, 1, y)").to_string(), "error: \"unable to resolve value `y`\"");
+        assert!(integrate("int(x, 0, 1, 1 / x)").to_string().starts_with("error: \"integrand is not finite"));
+    }
+}
@@ -0,0 +1,952 @@
+use crate::math::values::Value;
+use num::complex::Complex64;
+use num::{One, Zero};
+use std::ops::{Index, IndexMut};
+
+/// Pivots smaller than this, relative to the largest entry of the matrix,
+/// count as zero when deciding whether a matrix is singular and what its rank is.
+const SINGULAR_TOLERANCE: f64 = 1e-12;
+/// QR steps allowed for each eigenvalue to separate from the rest.
+const MAX_EIGENVALUE_ITERATIONS: usize = 100;
+/// Passes over every pair of columns allowed before singular values are given up on.
+const MAX_JACOBI_SWEEPS: usize = 100;
+/// The degree of the numerator and denominator approximating the matrix exponential.
+const PADE_DEGREE: usize = 6;
+
+/// A dense matrix of complex numbers.
+///
+/// Matrices are written as vectors of rows, such as `[[1, 2], [3, 4]]`, and
+/// only take this form while they are being computed with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Matrix {
+    pub rows: usize,
+    pub columns: usize,
+    /// The entries, one row after another.
+    entries: Vec<Complex64>,
+}
+
+/// The factors of `P * A = L * U`, where `P` reorders the rows of `A` so that
+/// every pivot is as large as possible.
+pub struct LowerUpper {
+    pub lower: Matrix,
+    pub upper: Matrix,
+    /// The row of `A` that ends up in each row of `P * A`.
+    pub permutation: Vec<usize>,
+    /// Whether `P` swaps an odd number of rows, which flips the sign of the determinant.
+    pub odd: bool,
+    /// Whether a pivot was too small to divide by.
+    pub singular: bool,
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = Complex64;
+
+    fn index(&self, (row, column): (usize, usize)) -> &Complex64 {
+        &self.entries[row * self.columns + column]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (row, column): (usize, usize)) -> &mut Complex64 {
+        &mut self.entries[row * self.columns + column]
+    }
+}
+
+impl Matrix {
+    pub fn zeros(rows: usize, columns: usize) -> Matrix {
+        Matrix {
+            rows,
+            columns,
+            entries: vec![Complex64::zero(); rows * columns],
+        }
+    }
+
+    pub fn identity(size: usize) -> Matrix {
+        let mut identity = Matrix::zeros(size, size);
+        for index in 0..size {
+            identity[(index, index)] = Complex64::one();
+        }
+        identity
+    }
+
+    /// Reads a vector of equally long vectors of numbers.
+    pub fn from_value(value: &Value) -> Result<Matrix, String> {
+        let Value::Vector(rows) = value else {
+            return Err(format!("{} is not a matrix", value));
+        };
+        let Some(Value::Vector(first)) = rows.first() else {
+            return Err("a matrix must be a vector of rows".to_string());
+        };
+        let mut matrix = Matrix::zeros(rows.len(), first.len());
+        for (index, row) in rows.iter().enumerate() {
+            let Value::Vector(row) = row else {
+                return Err("a matrix must be a vector of rows".to_string());
+            };
+            if row.len() != matrix.columns {
+                return Err("rows of a matrix must have same length".to_string());
+            }
+            for (column, entry) in row.iter().enumerate() {
+                match entry {
+                    Value::Number(number) => matrix[(index, column)] = *number,
+                    Value::Error(err) => return Err(err.clone()),
+                    _ => return Err("entries of a matrix must be numbers".to_string()),
+                }
+            }
+        }
+        if matrix.columns == 0 {
+            return Err("a matrix must have at least one column".to_string());
+        }
+        Ok(matrix)
+    }
+
+    /// Reads a vector of numbers as a matrix with a single column.
+    pub fn column_from_value(value: &Value) -> Result<Matrix, String> {
+        let Value::Vector(entries) = value else {
+            return Err(format!("{} is not a vector", value));
+        };
+        let entries = entries
+            .iter()
+            .map(|entry| match entry {
+                Value::Number(number) => Ok(*number),
+                Value::Error(err) => Err(err.clone()),
+                _ => Err("entries of a vector must be numbers".to_string()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Matrix {
+            rows: entries.len(),
+            columns: 1,
+            entries,
+        })
+    }
+
+    pub fn to_value(&self) -> Value {
+        Value::Vector(
+            self.entries
+                .chunks(self.columns)
+                .map(|row| Value::Vector(row.iter().copied().map(Value::Number).collect()))
+                .collect(),
+        )
+    }
+
+    /// The entries of a matrix with a single column or row, as a plain vector.
+    pub fn to_vector(&self) -> Value {
+        Value::Vector(self.entries.iter().copied().map(Value::Number).collect())
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.rows == self.columns
+    }
+
+    fn require_square(&self) -> Result<(), String> {
+        match self.is_square() {
+            true => Ok(()),
+            false => Err(format!("a {}x{} matrix is not square", self.rows, self.columns)),
+        }
+    }
+
+    /// The largest magnitude of any entry, which the tolerances are relative to.
+    fn scale(&self) -> f64 {
+        self.entries.iter().map(|entry| entry.norm()).fold(0.0, f64::max)
+    }
+
+    pub fn transpose(&self) -> Matrix {
+        let mut transposed = Matrix::zeros(self.columns, self.rows);
+        for row in 0..self.rows {
+            for column in 0..self.columns {
+                transposed[(column, row)] = self[(row, column)];
+            }
+        }
+        transposed
+    }
+
+    /// The transpose with every entry conjugated.
+    pub fn adjoint(&self) -> Matrix {
+        let mut adjoint = self.transpose();
+        for entry in &mut adjoint.entries {
+            *entry = entry.conj();
+        }
+        adjoint
+    }
+
+    pub fn mul(&self, other: &Matrix) -> Result<Matrix, String> {
+        if self.columns != other.rows {
+            return Err(format!(
+                "a {}x{} matrix can not be multiplied by a {}x{} matrix",
+                self.rows, self.columns, other.rows, other.columns
+            ));
+        }
+        let mut product = Matrix::zeros(self.rows, other.columns);
+        for row in 0..self.rows {
+            for inner in 0..self.columns {
+                let factor = self[(row, inner)];
+                if factor.is_zero() {
+                    continue;
+                }
+                for column in 0..other.columns {
+                    product[(row, column)] += factor * other[(inner, column)];
+                }
+            }
+        }
+        Ok(product)
+    }
+
+    /// Raises a square matrix to an integer power by repeated squaring. Negative
+    /// powers are powers of the inverse.
+    pub fn power(&self, exponent: i64) -> Result<Matrix, String> {
+        self.require_square()?;
+        let mut base = match exponent < 0 {
+            true => self.inverse()?,
+            false => self.clone(),
+        };
+        let mut exponent = exponent.unsigned_abs();
+        let mut result = Matrix::identity(self.rows);
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(&base)?;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul(&base)?;
+            }
+        }
+        Ok(result)
+    }
+
+    pub fn trace(&self) -> Result<Complex64, String> {
+        self.require_square()?;
+        Ok((0..self.rows).map(|index| self[(index, index)]).sum())
+    }
+
+    /// Factors a square matrix by Gaussian elimination with partial pivoting.
+    pub fn lower_upper(&self) -> Result<LowerUpper, String> {
+        self.require_square()?;
+        let size = self.rows;
+        let tolerance = SINGULAR_TOLERANCE * self.scale();
+        let mut upper = self.clone();
+        let mut lower = Matrix::identity(size);
+        let mut permutation: Vec<usize> = (0..size).collect();
+        let mut odd = false;
+        let mut singular = false;
+
+        for pivot in 0..size {
+            let best = (pivot..size)
+                .max_by(|&a, &b| upper[(a, pivot)].norm().total_cmp(&upper[(b, pivot)].norm()))
+                .unwrap_or(pivot);
+            if best != pivot {
+                for column in 0..size {
+                    upper.entries.swap(pivot * size + column, best * size + column);
+                }
+                // Only the multipliers found so far move along with their rows.
+                for column in 0..pivot {
+                    lower.entries.swap(pivot * size + column, best * size + column);
+                }
+                permutation.swap(pivot, best);
+                odd = !odd;
+            }
+            let value = upper[(pivot, pivot)];
+            if value.norm() <= tolerance {
+                singular = true;
+                continue;
+            }
+            for row in pivot + 1..size {
+                let factor = upper[(row, pivot)] / value;
+                lower[(row, pivot)] = factor;
+                for column in pivot..size {
+                    let above = upper[(pivot, column)];
+                    upper[(row, column)] -= factor * above;
+                }
+            }
+        }
+        Ok(LowerUpper {
+            lower,
+            upper,
+            permutation,
+            odd,
+            singular,
+        })
+    }
+
+    pub fn determinant(&self) -> Result<Complex64, String> {
+        let factors = self.lower_upper()?;
+        if factors.singular {
+            return Ok(Complex64::zero());
+        }
+        let product: Complex64 = (0..self.rows).map(|index| factors.upper[(index, index)]).product();
+        Ok(if factors.odd { -product } else { product })
+    }
+
+    pub fn inverse(&self) -> Result<Matrix, String> {
+        self.solve(&Matrix::identity(self.rows))
+    }
+
+    /// Finds `X` with `A * X = B`. When there are more equations than unknowns,
+    /// finds the `X` that comes closest in the least squares sense instead.
+    pub fn solve(&self, rhs: &Matrix) -> Result<Matrix, String> {
+        if rhs.rows != self.rows {
+            return Err(format!(
+                "the right side must have {} rows to match the matrix, not {}",
+                self.rows, rhs.rows
+            ));
+        }
+        if self.rows > self.columns {
+            return self.least_squares(rhs);
+        }
+        if self.rows < self.columns {
+            return Err(format!(
+                "a {}x{} matrix has fewer equations than unknowns",
+                self.rows, self.columns
+            ));
+        }
+
+        let factors = self.lower_upper()?;
+        if factors.singular {
+            return Err("matrix is singular".to_string());
+        }
+        let size = self.rows;
+        let mut solution = Matrix::zeros(size, rhs.columns);
+        for column in 0..rhs.columns {
+            // Forward substitution through `L`, then back substitution through `U`.
+            let mut values: Vec<Complex64> = factors.permutation.iter().map(|&row| rhs[(row, column)]).collect();
+            for row in 0..size {
+                for inner in 0..row {
+                    let known = values[inner];
+                    values[row] -= factors.lower[(row, inner)] * known;
+                }
+            }
+            for row in (0..size).rev() {
+                for inner in row + 1..size {
+                    let known = values[inner];
+                    values[row] -= factors.upper[(row, inner)] * known;
+                }
+                values[row] /= factors.upper[(row, row)];
+            }
+            for (row, value) in values.into_iter().enumerate() {
+                solution[(row, column)] = value;
+            }
+        }
+        Ok(solution)
+    }
+
+    fn least_squares(&self, rhs: &Matrix) -> Result<Matrix, String> {
+        let (q, r) = self.qr();
+        let tolerance = SINGULAR_TOLERANCE * self.scale();
+        if (0..self.columns).any(|index| r[(index, index)].norm() <= tolerance) {
+            return Err("the columns of the matrix are not independent".to_string());
+        }
+        let projected = q.adjoint().mul(rhs)?;
+        let mut solution = Matrix::zeros(self.columns, rhs.columns);
+        for column in 0..rhs.columns {
+            for row in (0..self.columns).rev() {
+                let mut value = projected[(row, column)];
+                for inner in row + 1..self.columns {
+                    value -= r[(row, inner)] * solution[(inner, column)];
+                }
+                solution[(row, column)] = value / r[(row, row)];
+            }
+        }
+        Ok(solution)
+    }
+
+    /// Factors the matrix into a unitary `Q` and an upper triangular `R` with
+    /// Householder reflections.
+    pub fn qr(&self) -> (Matrix, Matrix) {
+        let mut q = Matrix::identity(self.rows);
+        let mut r = self.clone();
+        for pivot in 0..self.columns.min(self.rows.saturating_sub(1)) {
+            let length = (pivot..self.rows).map(|row| r[(row, pivot)].norm_sqr()).sum::<f64>().sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            // Reflect onto the direction away from the leading entry, so the
+            // difference below never cancels out.
+            let leading = r[(pivot, pivot)];
+            let phase = match leading.is_zero() {
+                true => Complex64::one(),
+                false => leading / leading.norm(),
+            };
+            let mut reflector: Vec<Complex64> = (pivot..self.rows).map(|row| r[(row, pivot)]).collect();
+            reflector[0] += phase * length;
+            let norm = reflector.iter().map(|entry| entry.norm_sqr()).sum::<f64>().sqrt();
+            for entry in &mut reflector {
+                *entry /= norm;
+            }
+
+            // `H = I - 2 v v*` is applied to the rows of `R` and the columns of `Q`.
+            for column in 0..self.columns {
+                let dot: Complex64 = (pivot..self.rows).map(|row| reflector[row - pivot].conj() * r[(row, column)]).sum();
+                for row in pivot..self.rows {
+                    r[(row, column)] -= 2.0 * reflector[row - pivot] * dot;
+                }
+            }
+            for row in 0..self.rows {
+                let dot: Complex64 = (pivot..self.rows).map(|column| q[(row, column)] * reflector[column - pivot]).sum();
+                for column in pivot..self.rows {
+                    q[(row, column)] -= 2.0 * dot * reflector[column - pivot].conj();
+                }
+            }
+            for row in pivot + 1..self.rows {
+                r[(row, pivot)] = Complex64::zero();
+            }
+        }
+        (q, r)
+    }
+
+    /// How many rows are independent, found by reducing the matrix to row echelon form.
+    pub fn rank(&self) -> usize {
+        let tolerance = SINGULAR_TOLERANCE * self.scale();
+        let mut reduced = self.clone();
+        let mut rank = 0;
+        for column in 0..self.columns {
+            if rank == self.rows {
+                break;
+            }
+            let best = (rank..self.rows)
+                .max_by(|&a, &b| reduced[(a, column)].norm().total_cmp(&reduced[(b, column)].norm()))
+                .unwrap_or(rank);
+            if reduced[(best, column)].norm() <= tolerance {
+                continue;
+            }
+            for inner in 0..self.columns {
+                reduced.entries.swap(rank * self.columns + inner, best * self.columns + inner);
+            }
+            for row in rank + 1..self.rows {
+                let factor = reduced[(row, column)] / reduced[(rank, column)];
+                for inner in column..self.columns {
+                    let above = reduced[(rank, inner)];
+                    reduced[(row, inner)] -= factor * above;
+                }
+            }
+            rank += 1;
+        }
+        rank
+    }
+
+    /// Adds `factor` times `other` to a matrix of the same size.
+    fn add_scaled(&mut self, other: &Matrix, factor: Complex64) {
+        for (entry, addend) in self.entries.iter_mut().zip(&other.entries) {
+            *entry += factor * addend;
+        }
+    }
+
+    fn column_norm(&self, column: usize) -> f64 {
+        (0..self.rows).map(|row| self[(row, column)].norm_sqr()).sum::<f64>().sqrt()
+    }
+
+    /// Reduces a square matrix to upper Hessenberg form `H = Z* A Z`, zero below
+    /// the first subdiagonal, with Householder reflections.
+    fn hessenberg(&self) -> (Matrix, Matrix) {
+        let size = self.rows;
+        let mut h = self.clone();
+        let mut z = Matrix::identity(size);
+        for pivot in 0..size.saturating_sub(2) {
+            let length = (pivot + 1..size).map(|row| h[(row, pivot)].norm_sqr()).sum::<f64>().sqrt();
+            if length == 0.0 {
+                continue;
+            }
+            let leading = h[(pivot + 1, pivot)];
+            let phase = match leading.is_zero() {
+                true => Complex64::one(),
+                false => leading / leading.norm(),
+            };
+            let mut reflector: Vec<Complex64> = (pivot + 1..size).map(|row| h[(row, pivot)]).collect();
+            reflector[0] += phase * length;
+            let norm = reflector.iter().map(|entry| entry.norm_sqr()).sum::<f64>().sqrt();
+            for entry in &mut reflector {
+                *entry /= norm;
+            }
+
+            // `P = I - 2 v v*` is applied on both sides of `H`, and to the columns of `Z`.
+            for column in 0..size {
+                let dot: Complex64 = (pivot + 1..size).map(|row| reflector[row - pivot - 1].conj() * h[(row, column)]).sum();
+                for row in pivot + 1..size {
+                    h[(row, column)] -= 2.0 * reflector[row - pivot - 1] * dot;
+                }
+            }
+            for matrix in [&mut h, &mut z] {
+                for row in 0..size {
+                    let dot: Complex64 = (pivot + 1..size).map(|column| matrix[(row, column)] * reflector[column - pivot - 1]).sum();
+                    for column in pivot + 1..size {
+                        matrix[(row, column)] -= 2.0 * dot * reflector[column - pivot - 1].conj();
+                    }
+                }
+            }
+            for row in pivot + 2..size {
+                h[(row, pivot)] = Complex64::zero();
+            }
+        }
+        (h, z)
+    }
+
+    /// Finds the Schur form `T = Z* A Z` of a square matrix, upper triangular with
+    /// the eigenvalues on its diagonal, by the shifted QR algorithm on its Hessenberg form.
+    fn schur(&self) -> Result<(Matrix, Matrix), String> {
+        self.require_square()?;
+        let size = self.rows;
+        let (mut t, mut z) = self.hessenberg();
+        let mut high = size.saturating_sub(1);
+        let mut iterations = 0;
+        while high > 0 {
+            // The active block ends at `high` and starts after the last negligible subdiagonal entry.
+            let mut low = high;
+            while low > 0 {
+                let neighbours = t[(low - 1, low - 1)].norm() + t[(low, low)].norm();
+                if t[(low, low - 1)].norm() <= f64::EPSILON * neighbours.max(f64::MIN_POSITIVE) {
+                    t[(low, low - 1)] = Complex64::zero();
+                    break;
+                }
+                low -= 1;
+            }
+            if low == high {
+                high -= 1;
+                iterations = 0;
+                continue;
+            }
+            iterations += 1;
+            if iterations > MAX_EIGENVALUE_ITERATIONS {
+                return Err("eigenvalues did not converge".to_string());
+            }
+
+            // Shift by the eigenvalue of the trailing 2x2 block closest to its last
+            // entry, and now and then by something else to break out of cycles.
+            let (a, b) = (t[(high - 1, high - 1)], t[(high - 1, high)]);
+            let (c, d) = (t[(high, high - 1)], t[(high, high)]);
+            let shift = match iterations % 10 {
+                0 => d + t[(high, high - 1)].norm(),
+                _ => {
+                    let half = (a - d) / 2.0;
+                    let root = (half * half + b * c).sqrt();
+                    let (first, second) = (d + half + root, d + half - root);
+                    match (first - d).norm() <= (second - d).norm() {
+                        true => first,
+                        false => second,
+                    }
+                }
+            };
+
+            for index in low..=high {
+                t[(index, index)] -= shift;
+            }
+            let mut rotations = Vec::with_capacity(high - low);
+            for pivot in low..high {
+                let (x, y) = (t[(pivot, pivot)], t[(pivot + 1, pivot)]);
+                let length = (x.norm_sqr() + y.norm_sqr()).sqrt();
+                let (cos, sin) = match length == 0.0 {
+                    true => (Complex64::one(), Complex64::zero()),
+                    false => (x / length, y / length),
+                };
+                for column in pivot..size {
+                    let (upper, lower) = (t[(pivot, column)], t[(pivot + 1, column)]);
+                    t[(pivot, column)] = cos.conj() * upper + sin.conj() * lower;
+                    t[(pivot + 1, column)] = cos * lower - sin * upper;
+                }
+                rotations.push((cos, sin));
+            }
+            for (pivot, (cos, sin)) in (low..high).zip(rotations) {
+                for (matrix, rows) in [(&mut t, (pivot + 2).min(high + 1)), (&mut z, size)] {
+                    for row in 0..rows {
+                        let (left, right) = (matrix[(row, pivot)], matrix[(row, pivot + 1)]);
+                        matrix[(row, pivot)] = left * cos + right * sin;
+                        matrix[(row, pivot + 1)] = right * cos.conj() - left * sin.conj();
+                    }
+                }
+            }
+            for index in low..=high {
+                t[(index, index)] += shift;
+            }
+        }
+        Ok((t, z))
+    }
+
+    /// The eigenvalues of a square matrix, and a matrix whose columns are the
+    /// matching eigenvectors, each of length 1.
+    pub fn eigen(&self) -> Result<(Vec<Complex64>, Matrix), String> {
+        let (t, z) = self.schur()?;
+        let size = self.rows;
+        let values: Vec<Complex64> = (0..size).map(|index| t[(index, index)]).collect();
+        // A repeated eigenvalue would divide by zero, so it is nudged apart by a tiny amount.
+        let smallest = (f64::EPSILON * t.scale()).max(f64::MIN_POSITIVE);
+        let mut triangular = Matrix::zeros(size, size);
+        for (index, &value) in values.iter().enumerate() {
+            // Back substitution through `T - value I`, whose eigenvector ends at `index`.
+            triangular[(index, index)] = Complex64::one();
+            for row in (0..index).rev() {
+                let sum: Complex64 = (row + 1..=index).map(|inner| t[(row, inner)] * triangular[(inner, index)]).sum();
+                let mut pivot = t[(row, row)] - value;
+                if pivot.norm() < smallest {
+                    pivot = Complex64::new(smallest, 0.0);
+                }
+                triangular[(row, index)] = -sum / pivot;
+            }
+        }
+        let mut vectors = z.mul(&triangular)?;
+        for column in 0..size {
+            let norm = vectors.column_norm(column);
+            for row in 0..size {
+                vectors[(row, column)] /= norm;
+            }
+        }
+        Ok((values, vectors))
+    }
+
+    /// Factors the matrix as `U * diag(S) * V*` by one-sided Jacobi rotations,
+    /// with `S` the singular values from largest to smallest.
+    pub fn singular_values(&self) -> Result<(Matrix, Vec<f64>, Matrix), String> {
+        if self.rows < self.columns {
+            let (u, values, v) = self.adjoint().singular_values()?;
+            return Ok((v, values, u));
+        }
+        let mut u = self.clone();
+        let mut v = Matrix::identity(self.columns);
+        let mut sweeps = 0;
+        let mut rotated = true;
+        while rotated {
+            sweeps += 1;
+            if sweeps > MAX_JACOBI_SWEEPS {
+                return Err("singular values did not converge".to_string());
+            }
+            rotated = false;
+            for first in 0..self.columns {
+                for second in first + 1..self.columns {
+                    let alpha = u.column_norm(first).powi(2);
+                    let beta = u.column_norm(second).powi(2);
+                    let gamma: Complex64 = (0..self.rows).map(|row| u[(row, first)].conj() * u[(row, second)]).sum();
+                    if gamma.norm() <= f64::EPSILON * (alpha * beta).sqrt() || gamma.is_zero() {
+                        continue;
+                    }
+                    rotated = true;
+                    // A phase turns the second column so its product with the first is
+                    // real, and a real rotation then makes the two orthogonal.
+                    let phase = (gamma / gamma.norm()).conj();
+                    let zeta = (beta - alpha) / (2.0 * gamma.norm());
+                    let tangent = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                    let cos = 1.0 / (1.0 + tangent * tangent).sqrt();
+                    let sin = cos * tangent;
+                    for matrix in [&mut u, &mut v] {
+                        for row in 0..matrix.rows {
+                            let (left, right) = (matrix[(row, first)], phase * matrix[(row, second)]);
+                            matrix[(row, first)] = cos * left - sin * right;
+                            matrix[(row, second)] = sin * left + cos * right;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut order: Vec<usize> = (0..self.columns).collect();
+        let norms: Vec<f64> = order.iter().map(|&column| u.column_norm(column)).collect();
+        order.sort_by(|&a, &b| norms[b].total_cmp(&norms[a]));
+        let tolerance = SINGULAR_TOLERANCE * norms.iter().copied().fold(0.0, f64::max);
+        let mut left = Matrix::zeros(self.rows, self.columns);
+        let mut right = Matrix::zeros(self.columns, self.columns);
+        let mut values = Vec::with_capacity(self.columns);
+        for (index, &column) in order.iter().enumerate() {
+            let norm = norms[column];
+            values.push(norm);
+            for row in 0..self.columns {
+                right[(row, index)] = v[(row, column)];
+            }
+            if norm > tolerance {
+                for row in 0..self.rows {
+                    left[(row, index)] = u[(row, column)] / norm;
+                }
+            } else {
+                left.complete_column(index);
+            }
+        }
+        Ok((left, values, right))
+    }
+
+    /// Fills a column, that has no direction of its own, with a unit vector
+    /// orthogonal to the columns before it.
+    fn complete_column(&mut self, column: usize) {
+        let mut best = (0.0, vec![]);
+        for axis in 0..self.rows {
+            let mut candidate: Vec<Complex64> = (0..self.rows).map(|row| if row == axis { Complex64::one() } else { Complex64::zero() }).collect();
+            for previous in 0..column {
+                let dot: Complex64 = (0..self.rows).map(|row| self[(row, previous)].conj() * candidate[row]).sum();
+                for (row, entry) in candidate.iter_mut().enumerate() {
+                    *entry -= dot * self[(row, previous)];
+                }
+            }
+            let norm = candidate.iter().map(|entry| entry.norm_sqr()).sum::<f64>().sqrt();
+            if norm > best.0 {
+                best = (norm, candidate);
+            }
+        }
+        let (norm, candidate) = best;
+        for (row, entry) in candidate.into_iter().enumerate() {
+            self[(row, column)] = entry / norm;
+        }
+    }
+
+    /// The matrix exponential, by a Padé approximant of a matrix scaled down by
+    /// a power of two, squared back up as many times.
+    pub fn exponential(&self) -> Result<Matrix, String> {
+        self.require_square()?;
+        let size = self.rows;
+        let largest_row = (0..size)
+            .map(|row| (0..size).map(|column| self[(row, column)].norm()).sum::<f64>())
+            .fold(0.0, f64::max);
+        let squarings = match largest_row > 0.5 {
+            true => (largest_row.log2().ceil() as i32 + 1).max(0),
+            false => 0,
+        };
+        let mut scaled = self.clone();
+        for entry in &mut scaled.entries {
+            *entry /= 2f64.powi(squarings);
+        }
+
+        let mut numerator = Matrix::identity(size);
+        let mut denominator = Matrix::identity(size);
+        let mut power = Matrix::identity(size);
+        let mut coefficient = 1.0;
+        for degree in 1..=PADE_DEGREE {
+            coefficient *= (PADE_DEGREE - degree + 1) as f64 / (degree * (2 * PADE_DEGREE - degree + 1)) as f64;
+            power = scaled.mul(&power)?;
+            numerator.add_scaled(&power, Complex64::new(coefficient, 0.0));
+            let sign = if degree % 2 == 0 { 1.0 } else { -1.0 };
+            denominator.add_scaled(&power, Complex64::new(sign * coefficient, 0.0));
+        }
+        let mut exponential = denominator.solve(&numerator)?;
+        for _ in 0..squarings {
+            exponential = exponential.mul(&exponential)?;
+        }
+        Ok(exponential)
+    }
+}
+
+impl Value {
+    /// Whether the value is written as a matrix, a vector whose entries are all vectors.
+    pub fn is_matrix(&self) -> bool {
+        matches!(self, Value::Vector(rows) if !rows.is_empty() && rows.iter().all(|row| matches!(row, Value::Vector(_))))
+    }
+
+    /// Multiplies two matrices, or a matrix and a vector standing for a column on
+    /// the right or a row on the left.
+    pub fn matrix_product(lhs: &Value, rhs: &Value) -> Value {
+        let product = match (lhs.is_matrix(), rhs.is_matrix()) {
+            (true, true) => Matrix::from_value(lhs)
+                .and_then(|lhs| lhs.mul(&Matrix::from_value(rhs)?))
+                .map(|product| product.to_value()),
+            (true, false) => Matrix::from_value(lhs)
+                .and_then(|lhs| lhs.mul(&Matrix::column_from_value(rhs)?))
+                .map(|product| product.to_vector()),
+            _ => Matrix::column_from_value(lhs)
+                .and_then(|lhs| lhs.transpose().mul(&Matrix::from_value(rhs)?))
+                .map(|product| product.to_vector()),
+        };
+        product.unwrap_or_else(Value::Error)
+    }
+
+    pub fn matrix_power(&self, exponent: &Complex64) -> Value {
+        if exponent.im != 0.0 || exponent.re.fract() != 0.0 {
+            return Value::Error("matrices can only be raised to integer powers".to_string());
+        }
+        Matrix::from_value(self)
+            .and_then(|matrix| matrix.power(exponent.re as i64))
+            .map_or_else(Value::Error, |power| power.to_value())
+    }
+
+    /// Transposes a matrix, or turns a vector, standing for a column, into a single row.
+    pub fn transpose(&self) -> Value {
+        if matches!(self, Value::Vector(_)) && !self.is_matrix() {
+            return Matrix::column_from_value(self).map_or_else(Value::Error, |column| column.transpose().to_value());
+        }
+        matrix_op(self, |matrix| Ok(matrix.transpose().to_value()))
+    }
+
+    pub fn determinant(&self) -> Value {
+        matrix_op(self, |matrix| Ok(Value::Number(matrix.determinant()?)))
+    }
+
+    pub fn inverse(&self) -> Value {
+        matrix_op(self, |matrix| Ok(matrix.inverse()?.to_value()))
+    }
+
+    pub fn rank(&self) -> Value {
+        matrix_op(self, |matrix| Ok(Value::Number(Complex64::new(matrix.rank() as f64, 0.0))))
+    }
+
+    pub fn trace(&self) -> Value {
+        matrix_op(self, |matrix| Ok(Value::Number(matrix.trace()?)))
+    }
+
+    /// Returns `[P, L, U]` with `P * A = L * U`.
+    pub fn lower_upper(&self) -> Value {
+        matrix_op(self, |matrix| {
+            let factors = matrix.lower_upper()?;
+            let mut permutation = Matrix::zeros(matrix.rows, matrix.rows);
+            for (row, &original) in factors.permutation.iter().enumerate() {
+                permutation[(row, original)] = Complex64::one();
+            }
+            Ok(Value::Vector(vec![
+                permutation.to_value(),
+                factors.lower.to_value(),
+                factors.upper.to_value(),
+            ]))
+        })
+    }
+
+    /// Returns `[Q, R]` with `A = Q * R`.
+    pub fn qr(&self) -> Value {
+        matrix_op(self, |matrix| {
+            let (q, r) = matrix.qr();
+            Ok(Value::Vector(vec![q.to_value(), r.to_value()]))
+        })
+    }
+
+    /// Solves `A * x = b` for a vector `b`, or for every column of a matrix `b`.
+    pub fn solve_linear(lhs: &Value, rhs: &Value) -> Value {
+        let solution = Matrix::from_value(lhs).and_then(|matrix| match rhs.is_matrix() {
+            true => Ok(matrix.solve(&Matrix::from_value(rhs)?)?.to_value()),
+            false => Ok(matrix.solve(&Matrix::column_from_value(rhs)?)?.to_vector()),
+        });
+        solution.unwrap_or_else(Value::Error)
+    }
+
+    /// Returns `[values, V]`, where column `i` of `V` is an eigenvector for `values[i]`.
+    pub fn eigen(&self) -> Value {
+        matrix_op(self, |matrix| {
+            let (values, vectors) = matrix.eigen()?;
+            Ok(Value::Vector(vec![
+                Value::Vector(values.into_iter().map(Value::Number).collect()),
+                vectors.to_value(),
+            ]))
+        })
+    }
+
+    /// Returns `[U, S, V]` with `A = U * diag(S) * V*`, where `S` holds the
+    /// singular values from largest to smallest.
+    pub fn singular_values(&self) -> Value {
+        matrix_op(self, |matrix| {
+            let (u, values, v) = matrix.singular_values()?;
+            let values = values.into_iter().map(|value| Value::Number(Complex64::new(value, 0.0)));
+            Ok(Value::Vector(vec![u.to_value(), Value::Vector(values.collect()), v.to_value()]))
+        })
+    }
+
+    pub fn matrix_exponential(&self) -> Value {
+        matrix_op(self, |matrix| Ok(matrix.exponential()?.to_value()))
+    }
+}
+
+fn matrix_op(value: &Value, operation: impl FnOnce(Matrix) -> Result<Value, String>) -> Value {
+    if let Value::Error(_) = value {
+        return value.clone();
+    }
+    Matrix::from_value(value).and_then(operation).unwrap_or_else(Value::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(rows: &[&[f64]]) -> Matrix {
+        let mut matrix = Matrix::zeros(rows.len(), rows[0].len());
+        for (row, entries) in rows.iter().enumerate() {
+            for (column, &entry) in entries.iter().enumerate() {
+                matrix[(row, column)] = Complex64::new(entry, 0.0);
+            }
+        }
+        matrix
+    }
+
+    fn assert_close(actual: &Matrix, expected: &Matrix) {
+        assert_eq!((actual.rows, actual.columns), (expected.rows, expected.columns));
+        for (actual_entry, expected_entry) in actual.entries.iter().zip(&expected.entries) {
+            assert!((actual_entry - expected_entry).norm() < 1e-9, "{:?} is not {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn determinant() {
+        let determinant = matrix(&[&[1.0, 2.0], &[3.0, 4.0]]).determinant().unwrap();
+        assert!((determinant - Complex64::new(-2.0, 0.0)).norm() < 1e-12);
+        let singular = matrix(&[&[1.0, 2.0], &[2.0, 4.0]]);
+        assert_eq!(singular.determinant().unwrap(), Complex64::zero());
+        assert!(matrix(&[&[1.0, 2.0]]).determinant().is_err());
+    }
+
+    #[test]
+    fn inverse_and_solve() {
+        let a = matrix(&[&[4.0, 7.0], &[2.0, 6.0]]);
+        assert_close(&a.inverse().unwrap(), &matrix(&[&[0.6, -0.7], &[-0.2, 0.4]]));
+        assert_close(&a.mul(&a.inverse().unwrap()).unwrap(), &Matrix::identity(2));
+        assert!(matrix(&[&[1.0, 2.0], &[2.0, 4.0]]).inverse().is_err());
+
+        let b = matrix(&[&[1.0], &[2.0]]);
+        assert_close(&a.mul(&a.solve(&b).unwrap()).unwrap(), &b);
+    }
+
+    #[test]
+    fn lower_upper_reorders_rows() {
+        let a = matrix(&[&[0.0, 1.0], &[2.0, 3.0]]);
+        let factors = a.lower_upper().unwrap();
+        let mut reordered = Matrix::zeros(2, 2);
+        for (row, &original) in factors.permutation.iter().enumerate() {
+            for column in 0..2 {
+                reordered[(row, column)] = a[(original, column)];
+            }
+        }
+        assert_close(&factors.lower.mul(&factors.upper).unwrap(), &reordered);
+        assert!(factors.odd);
+    }
+
+    #[test]
+    fn products_powers_and_rank() {
+        let a = matrix(&[&[1.0, 1.0], &[0.0, 1.0]]);
+        assert_close(&a.power(3).unwrap(), &matrix(&[&[1.0, 3.0], &[0.0, 1.0]]));
+        assert_close(&a.power(-1).unwrap(), &matrix(&[&[1.0, -1.0], &[0.0, 1.0]]));
+        assert!(a.mul(&matrix(&[&[1.0, 2.0, 3.0]])).is_err());
+        assert_eq!(matrix(&[&[1.0, 2.0], &[2.0, 4.0], &[3.0, 6.0]]).rank(), 1);
+        assert_eq!(a.rank(), 2);
+    }
+
+    #[test]
+    fn eigenvalues_and_eigenvectors() {
+        for a in [
+            matrix(&[&[2.0, 1.0], &[1.0, 2.0]]),
+            matrix(&[&[1.0, 2.0], &[3.0, 4.0]]),
+            matrix(&[&[0.0, -1.0], &[1.0, 0.0]]),
+            matrix(&[&[2.0, 1.0, 0.0], &[0.0, 2.0, 1.0], &[1.0, 0.0, 3.0]]),
+        ] {
+            let (values, vectors) = a.eigen().unwrap();
+            let mut scaled = vectors.clone();
+            for (column, value) in values.iter().enumerate() {
+                for row in 0..a.rows {
+                    scaled[(row, column)] *= value;
+                }
+            }
+            assert_close(&a.mul(&vectors).unwrap(), &scaled);
+        }
+
+        let (mut values, _) = matrix(&[&[0.0, -1.0], &[1.0, 0.0]]).eigen().unwrap();
+        values.sort_by(|a, b| a.im.total_cmp(&b.im));
+        assert!((values[0] + Complex64::i()).norm() < 1e-12 && (values[1] - Complex64::i()).norm() < 1e-12);
+    }
+
+    #[test]
+    fn singular_values() {
+        let a = matrix(&[&[3.0, 0.0], &[4.0, 5.0], &[0.0, 0.0]]);
+        let (u, values, v) = a.singular_values().unwrap();
+        let mut scaled = u.clone();
+        for (column, value) in values.iter().enumerate() {
+            for row in 0..u.rows {
+                scaled[(row, column)] *= value;
+            }
+        }
+        assert_close(&scaled.mul(&v.adjoint()).unwrap(), &a);
+        assert!((values[0] - 45f64.sqrt()).abs() < 1e-9 && (values[1] - 5f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn exponential() {
+        let nilpotent = matrix(&[&[0.0, 1.0], &[0.0, 0.0]]);
+        assert_close(&nilpotent.exponential().unwrap(), &matrix(&[&[1.0, 1.0], &[0.0, 1.0]]));
+        let diagonal = matrix(&[&[1.0, 0.0], &[0.0, -2.0]]);
+        assert_close(&diagonal.exponential().unwrap(), &matrix(&[&[1f64.exp(), 0.0], &[0.0, (-2f64).exp()]]));
+        let rotation = matrix(&[&[0.0, -3.0], &[3.0, 0.0]]);
+        let (cos, sin) = (3f64.cos(), 3f64.sin());
+        assert_close(&rotation.exponential().unwrap(), &matrix(&[&[cos, -sin], &[sin, cos]]));
+    }
+}
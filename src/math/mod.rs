@@ -1,4 +1,12 @@
 pub mod context;
+mod derive;
 mod evaluation;
+mod geometry;
 pub mod expr;
+pub mod idx;
+mod integrate;
+pub mod matrix;
+mod roots;
+mod solve;
+pub mod special;
 pub mod values;
@@ -0,0 +1,182 @@
+use crate::math::context::Context;
+use crate::math::expr::Expression;
+use crate::math::values::Value;
+use num::complex::Complex64;
+use num::Zero;
+use std::f64::consts::TAU;
+
+const MAX_ITERATIONS: usize = 500;
+const STEP_TOLERANCE: f64 = 4.0 * f64::EPSILON;
+/// How many times the rounding error of evaluating the polynomial a residual may
+/// be and still count as zero. Repeated roots can not be found more precisely.
+const RESIDUAL_TOLERANCE: f64 = 8.0 * f64::EPSILON;
+/// Parts of a root smaller than this, relative to its size, are rounded to zero.
+const CLEANUP_TOLERANCE: f64 = 1e-12;
+
+impl Expression {
+    /// Finds every complex root of `polynomial` in `variable`, repeated by multiplicity.
+    ///
+    /// The coefficients are read off symbolically and evaluated, after which all
+    /// roots are refined together with the Aberth–Ehrlich method.
+    pub fn evaluate_roots(polynomial: &Expression, variable: &Expression, ctx: &mut Context) -> Value {
+        let Expression::Literal {
+            content: ref variable_name,
+            ..
+        } = variable
+        else {
+            return Value::Error("variables must be a literal".to_string());
+        };
+        let coefficients = match polynomial.coefficients(variable_name, ctx) {
+            Ok(coefficients) => coefficients,
+            Err(err) => return Value::Error(err),
+        };
+
+        let mut values = Vec::with_capacity(coefficients.len());
+        for coefficient in &coefficients {
+            match coefficient.eval(ctx) {
+                Value::Number(value) if value.is_finite() => values.push(value),
+                Value::Number(_) => return Value::Error("coefficients of the polynomial must be finite".to_string()),
+                Value::Error(err) => return Value::Error(err),
+                _ => return Value::Error("coefficients of the polynomial must be numbers".to_string()),
+            }
+        }
+        while values.last().is_some_and(Complex64::is_zero) {
+            values.pop();
+        }
+        if values.is_empty() {
+            return Value::Error("every value is a root of the zero polynomial".to_string());
+        }
+
+        // A factor of `x^n` is split off first, as those roots are exact.
+        let zeros = values.iter().take_while(|value| value.is_zero()).count();
+        let mut roots = vec![Complex64::zero(); zeros];
+        match aberth(&values[zeros..]) {
+            Ok(found) => roots.extend(found.into_iter().map(clean)),
+            Err(err) => return Value::Error(err),
+        }
+        roots.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        Value::Vector(roots.into_iter().map(Value::Number).collect())
+    }
+}
+
+/// Refines all roots of the polynomial with the given coefficients, lowest degree
+/// first, at the same time. Each step is a Newton step corrected by the pull of
+/// every other approximation, which keeps them from converging onto the same root.
+fn aberth(coefficients: &[Complex64]) -> Result<Vec<Complex64>, String> {
+    let degree = coefficients.len() - 1;
+    if degree == 0 {
+        return Ok(vec![]);
+    }
+    let leading = coefficients[degree];
+    let monic: Vec<Complex64> = coefficients.iter().map(|value| value / leading).collect();
+
+    // Every root lies within the Cauchy bound, so start on a circle of that
+    // radius, turned slightly so real polynomials do not start out symmetric.
+    let radius = 1.0 + monic[..degree].iter().map(|value| value.norm()).fold(0.0, f64::max);
+    let mut roots: Vec<Complex64> = (0..degree)
+        .map(|k| Complex64::from_polar(radius, TAU * k as f64 / degree as f64 + 0.4))
+        .collect();
+
+    let mut converged = vec![false; degree];
+    for _ in 0..MAX_ITERATIONS {
+        for k in 0..degree {
+            if converged[k] {
+                continue;
+            }
+            let (value, slope, bound) = horner(&monic, roots[k]);
+            if value.norm() <= RESIDUAL_TOLERANCE * bound {
+                converged[k] = true;
+                continue;
+            }
+            let newton = value / slope;
+            let repulsion: Complex64 = (0..degree)
+                .filter(|&j| j != k)
+                .map(|j| (roots[k] - roots[j]).inv())
+                .sum();
+            let step = newton / (Complex64::new(1.0, 0.0) - newton * repulsion);
+            if !step.is_finite() {
+                continue;
+            }
+            roots[k] -= step;
+            converged[k] = step.norm() <= STEP_TOLERANCE * roots[k].norm();
+        }
+        if converged.iter().all(|&done| done) {
+            return Ok(roots);
+        }
+    }
+    Err(format!("roots did not converge after {} iterations", MAX_ITERATIONS))
+}
+
+/// Evaluates the polynomial and its derivative at `z`, along with the sum of the
+/// absolute values of its terms, which bounds the rounding error of the value.
+fn horner(coefficients: &[Complex64], z: Complex64) -> (Complex64, Complex64, f64) {
+    let mut value = Complex64::zero();
+    let mut slope = Complex64::zero();
+    let mut bound = 0.0;
+    for coefficient in coefficients.iter().rev() {
+        slope = slope * z + value;
+        value = value * z + coefficient;
+        bound = bound * z.norm() + coefficient.norm();
+    }
+    (value, slope, bound)
+}
+
+fn clean(root: Complex64) -> Complex64 {
+    let scale = root.norm() * CLEANUP_TOLERANCE;
+    let round = |part: f64| if part.abs() < scale { 0.0 } else { part };
+    Complex64::new(round(root.re), round(root.im))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::context::Context;
+    use crate::math::expr::Expression;
+    use crate::math::values::Value;
+    use num::complex::Complex64;
+
+    fn roots(source: &str) -> Value {
+        Expression::parse(source).unwrap().eval(&mut Context::default())
+    }
+
+    fn assert_roots(value: Value, expected: &[Complex64], tolerance: f64) {
+        let Value::Vector(found) = &value else {
+            panic!("expected roots, got {}", value);
+        };
+        assert_eq!(found.len(), expected.len(), "roots {}", value);
+        for (root, expected) in found.iter().zip(expected) {
+            let Value::Number(root) = root else {
+                panic!("expected roots, got {}", value);
+            };
+            assert!((root - expected).norm() <= tolerance, "roots {}", value);
+        }
+    }
+
+    fn real(values: &[f64]) -> Vec<Complex64> {
+        values.iter().map(|&re| Complex64::new(re, 0.0)).collect()
+    }
+
+    #[test]
+    fn finds_real_and_complex_roots() {
+        assert_roots(roots("roots(x^2 - 4, x)"), &real(&[-2.0, 2.0]), 1e-12);
+        assert_roots(roots("roots(x^3 - 6 * x^2 + 11 * x - 6, x)"), &real(&[1.0, 2.0, 3.0]), 1e-9);
+        assert_roots(roots("roots(x^2 + 1, x)"), &[-Complex64::i(), Complex64::i()], 1e-12);
+    }
+
+    #[test]
+    fn repeats_roots_by_multiplicity() {
+        assert_roots(roots("roots(x^3, x)"), &real(&[0.0, 0.0, 0.0]), 0.0);
+        assert_roots(roots("roots((x - 1)^2, x)"), &real(&[1.0, 1.0]), 1e-6);
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_polynomial() {
+        assert_eq!(
+            roots("roots(sin(x), x)").to_string(),
+            "error: \"sin(x) is not a polynomial in x\""
+        );
+        assert_eq!(
+            roots("roots(x - x, x)").to_string(),
+            "error: \"every value is a root of the zero polynomial\""
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use crate::math::context::Context;
+use crate::math::expr::{BinaryOperation, Expression};
+use crate::math::values::Value;
+use num::complex::Complex64;
+
+/// How many intervals the range is split into while looking for roots.
+const SAMPLES: usize = 1000;
+const MAX_ITERATIONS: usize = 100;
+/// How close to zero the residual must be for a point to count as a root.
+const RESIDUAL_TOLERANCE: f64 = 1e-8;
+const STEP_TOLERANCE: f64 = 4.0 * f64::EPSILON;
+/// The most roots listed before an equation counts as having too many to be useful.
+const MAX_ROOTS: usize = 100;
+/// How many significant digits a root is rounded to when trying a simpler value.
+const SNAP_DIGITS: i32 = 10;
+
+impl Expression {
+    /// Finds every root of `equation` whose real part lies between `minimum` and `maximum`.
+    ///
+    /// An equation `lhs == rhs` is solved as `lhs - rhs == 0`, anything else is
+    /// solved for zero. The editor types `=` as an assignment, so `lhs = rhs`
+    /// counts as an equation too, rather than storing into the variable. Sign changes along the real axis are refined by bisection
+    /// and secant steps, while dips in the residual that never cross zero are
+    /// followed into the complex plane with Newton's method.
+    pub fn evaluate_solve(
+        minimum: &Expression,
+        maximum: &Expression,
+        variable: &Expression,
+        equation: &Expression,
+        ctx: &mut Context,
+    ) -> Value {
+        let Expression::Literal {
+            content: ref variable_name,
+            ..
+        } = variable
+        else {
+            return Value::Error("variables must be a literal".to_string());
+        };
+        let Value::Number(min_val) = minimum.eval(ctx) else {
+            return Value::Error("minimum of solve must be a number".to_string());
+        };
+        let Value::Number(max_val) = maximum.eval(ctx) else {
+            return Value::Error("maximum of solve must be a number".to_string());
+        };
+        if min_val.im != 0.0 || max_val.im != 0.0 {
+            return Value::Error("solve range can not be complex".to_string());
+        }
+        if min_val.re >= max_val.re {
+            return Value::Error("solve maximum must be larger than minimum".to_string());
+        }
+
+        ctx.push_frame();
+        let mut solver = Solver {
+            variable: variable_name,
+            equation,
+            ctx,
+            last_error: None,
+        };
+        let result = solver.solve(min_val.re, max_val.re);
+        ctx.pop_frame();
+        result
+    }
+}
+
+struct Solver<'a> {
+    variable: &'a String,
+    equation: &'a Expression,
+    ctx: &'a mut Context,
+    last_error: Option<Value>,
+}
+
+impl Solver<'_> {
+    fn residual(&mut self, x: Complex64) -> Option<Complex64> {
+        self.ctx.set_variable(self.variable.clone(), Value::Number(x));
+        let value = match self.equation {
+            Expression::Binary {
+                op: BinaryOperation::Equal | BinaryOperation::Store,
+                lhs,
+                rhs,
+                ..
+            } => Value::sub(&lhs.eval(self.ctx), &rhs.eval(self.ctx)),
+            _ => self.equation.eval(self.ctx),
+        };
+        match value {
+            Value::Number(num) if num.is_finite() => Some(num),
+            Value::Number(_) => None,
+            error => {
+                self.last_error = Some(error);
+                None
+            }
+        }
+    }
+
+    fn solve(&mut self, min: f64, max: f64) -> Value {
+        let step = (max - min) / SAMPLES as f64;
+        let samples: Vec<(f64, Option<Complex64>)> = (0..=SAMPLES)
+            .map(|index| {
+                let x = min + step * index as f64;
+                (x, self.residual(Complex64::new(x, 0.0)))
+            })
+            .collect();
+
+        if samples.iter().all(|(_, residual)| residual.is_none()) {
+            return self.last_error.clone().unwrap_or_else(|| {
+                Value::Error("equation is not finite anywhere in the range".to_string())
+            });
+        }
+
+        if samples.iter().all(|(_, residual)| residual.is_none_or(|residual| residual.norm() == 0.0)) {
+            return Value::Error("equation holds for every value in the range".to_string());
+        }
+
+        let mut roots: Vec<Complex64> = vec![];
+        let mut closest: Option<(Complex64, f64)> = None;
+
+        for (index, (x, residual)) in samples.iter().enumerate() {
+            let Some(fx) = residual else {
+                continue;
+            };
+
+            if fx.norm() == 0.0 {
+                push_root(&mut roots, Complex64::new(*x, 0.0));
+                continue;
+            }
+
+            if let Some((next_x, Some(next_fx))) = samples.get(index + 1) {
+                if is_real(*fx) && is_real(*next_fx) && fx.re.signum() != next_fx.re.signum() {
+                    if let Some(root) = self.bisect(*x, fx.re, *next_x, next_fx.re) {
+                        let root = self.polish(Complex64::new(root, 0.0));
+                        push_root(&mut roots, root);
+                    }
+                    continue;
+                }
+            }
+
+            // A dip in the residual that does not cross zero may hide a double
+            // root or a pair of complex roots nearby.
+            let previous = index.checked_sub(1).and_then(|index| samples[index].1);
+            let next = samples.get(index + 1).and_then(|sample| sample.1);
+            let is_dip = previous.is_none_or(|previous| previous.norm() > fx.norm())
+                && next.is_none_or(|next| next.norm() > fx.norm());
+            if !is_dip {
+                continue;
+            }
+            // Starting off the real axis at skewed angles keeps Newton's method from
+            // being trapped on an axis of symmetry of the residual.
+            for radius in [step, 1.0, (max - min) / 4.0] {
+                for angle in [1.0, 2.0, -1.0, -2.0] {
+                    match self.newton(x + Complex64::from_polar(radius, angle)) {
+                        Ok(root) if root.re >= min && root.re <= max => {
+                            let root = self.polish(root);
+                            push_root(&mut roots, root);
+                        }
+                        Ok(_) => {}
+                        Err((z, residual)) => {
+                            if closest.is_none_or(|(_, best)| residual < best) {
+                                closest = Some((z, residual));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if roots.is_empty() {
+            if let Some((z, residual)) = closest {
+                return Value::Error(format!(
+                    "solve did not converge, the closest point was {} with a residual of {:e}",
+                    z, residual
+                ));
+            }
+        }
+
+        if roots.len() > MAX_ROOTS {
+            return Value::Error(format!("equation has more than {} roots in the range", MAX_ROOTS));
+        }
+        roots.sort_by(|a, b| a.re.total_cmp(&b.re).then(a.im.total_cmp(&b.im)));
+        Value::Vector(roots.into_iter().map(Value::Number).collect())
+    }
+
+    /// Narrows a sign change between `a` and `b` down to a root, rejecting
+    /// poles where the residual grows instead of vanishing.
+    fn bisect(&mut self, mut a: f64, mut fa: f64, mut b: f64, mut fb: f64) -> Option<f64> {
+        for iteration in 0..MAX_ITERATIONS {
+            let secant = b - fb * (b - a) / (fb - fa);
+            let c = if iteration % 2 == 0 && secant > a.min(b) && secant < a.max(b) {
+                secant
+            } else {
+                (a + b) / 2.0
+            };
+            let fc = self.residual(Complex64::new(c, 0.0))?.re;
+
+            if fc == 0.0 || (b - a).abs() < STEP_TOLERANCE * (1.0 + c.abs()) {
+                let residual = self.residual(Complex64::new(c, 0.0))?;
+                return (residual.norm() < RESIDUAL_TOLERANCE).then_some(c);
+            }
+            if fc.signum() == fa.signum() {
+                a = c;
+                fa = fc;
+            } else {
+                b = c;
+                fb = fc;
+            }
+        }
+        None
+    }
+
+    /// Prefers the root rounded to a few significant digits when the equation
+    /// holds there at least as well, so rounding in the residual does not turn
+    /// an exact root like 2 into 1.999999999999999.
+    fn polish(&mut self, root: Complex64) -> Complex64 {
+        let snapped = Complex64::new(snap(root.re), snap(root.im));
+        match (self.residual(snapped), self.residual(root)) {
+            (Some(snapped_residual), Some(residual)) if snapped_residual.norm() <= residual.norm() => snapped,
+            _ => root,
+        }
+    }
+
+    /// Runs Newton's method from `z` using a numerical derivative.
+    /// On failure, returns the last point reached and its residual.
+    fn newton(&mut self, mut z: Complex64) -> Result<Complex64, (Complex64, f64)> {
+        let mut fz = self.residual(z).ok_or((z, f64::INFINITY))?;
+        for _ in 0..MAX_ITERATIONS {
+            let h = 1e-6 * (1.0 + z.norm());
+            let forward = self.residual(z + h).ok_or((z, fz.norm()))?;
+            let backward = self.residual(z - h).ok_or((z, fz.norm()))?;
+            let derivative = (forward - backward) / (2.0 * h);
+            if derivative.norm() == 0.0 {
+                return Err((z, fz.norm()));
+            }
+
+            let step = fz / derivative;
+            z -= step;
+            fz = self.residual(z).ok_or((z, f64::INFINITY))?;
+            if step.norm() < STEP_TOLERANCE * (1.0 + z.norm()) || fz.norm() == 0.0 {
+                break;
+            }
+        }
+
+        if fz.norm() >= RESIDUAL_TOLERANCE {
+            return Err((z, fz.norm()));
+        }
+        if z.im.abs() < 1e-9 * (1.0 + z.re.abs()) {
+            z.im = 0.0;
+        }
+        if z.re.abs() < 1e-9 * (1.0 + z.im.abs()) {
+            z.re = 0.0;
+        }
+        Ok(z)
+    }
+}
+
+fn snap(value: f64) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+    let scale = 10f64.powi(SNAP_DIGITS - 1 - value.abs().log10().floor() as i32);
+    (value * scale).round() / scale
+}
+
+fn is_real(value: Complex64) -> bool {
+    value.im.abs() <= 1e-12 * (1.0 + value.re.abs())
+}
+
+fn push_root(roots: &mut Vec<Complex64>, root: Complex64) {
+    let duplicate = roots
+        .iter()
+        .any(|existing| (existing - root).norm() < 1e-6 * (1.0 + root.norm()));
+    if !duplicate {
+        roots.push(root);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::context::Context;
+    use crate::math::expr::Expression;
+    use crate::math::values::Value;
+
+    fn evaluate(source: &str, ctx: &mut Context) -> Value {
+        Expression::parse(source).unwrap().eval(ctx)
+    }
+
+    fn assert_roots(value: Value, expected: &[f64]) {
+        let Value::Vector(roots) = &value else {
+            panic!("expected roots, got {}", value);
+        };
+        assert_eq!(roots.len(), expected.len(), "{}", value);
+        for (root, expected) in roots.iter().zip(expected) {
+            let Value::Number(root) = root else {
+                panic!("expected roots, got {}", value);
+            };
+            assert!((root - expected).norm() < 1e-9, "{}", value);
+        }
+    }
+
+    #[test]
+    fn finds_real_roots() {
+        let mut ctx = Context::default();
+        assert_roots(evaluate("solve(x, -5, 5, x^2 - 4)", &mut ctx), &[-2.0, 2.0]);
+        assert_roots(evaluate("solve(x, -5, 5, x^2 == 4)", &mut ctx), &[-2.0, 2.0]);
+    }
+
+    #[test]
+    fn exact_roots_come_out_exact() {
+        let mut ctx = Context::default();
+        assert_eq!(evaluate("solve(x, -3, 3, x^2 - 4)", &mut ctx).to_string(), "<-2+0i,  2+0i,  >");
+        assert_eq!(evaluate("solve(x, 0, 1, 10 * x - 3)", &mut ctx).to_string(), "<0.3+0i,  >");
+        assert_eq!(evaluate("solve(x, -3, 3, x^2 + 1)", &mut ctx).to_string(), "<0-1i,  0+1i,  >");
+        // Roots that are not simple numbers are left as found.
+        let Value::Vector(roots) = evaluate("solve(x, 0, 3, x^2 - 2)", &mut ctx) else {
+            panic!("expected roots");
+        };
+        let [Value::Number(root)] = roots.as_slice() else {
+            panic!("expected a single root");
+        };
+        assert!((root.re - std::f64::consts::SQRT_2).abs() < 1e-12, "{}", root);
+    }
+
+    #[test]
+    fn assignment_is_an_equation() {
+        let mut ctx = Context::default();
+        evaluate("x = 7", &mut ctx);
+        assert_roots(evaluate("solve(x, -5, 5, x^2 = 4)", &mut ctx), &[-2.0, 2.0]);
+        assert_eq!(evaluate("x", &mut ctx).to_string(), "7+0i");
+    }
+
+    #[test]
+    fn identities_are_rejected() {
+        let mut ctx = Context::default();
+        assert_eq!(
+            evaluate("solve(x, -5, 5, x == x)", &mut ctx).to_string(),
+            "error: \"equation holds for every value in the range\""
+        );
+        assert_eq!(
+            evaluate("solve(x, 1, 1000, sin(x))", &mut ctx).to_string(),
+            "error: \"equation has more than 100 roots in the range\""
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use num::complex::Complex64;
+use std::f64::consts::PI;
+
+const LANCZOS_G: f64 = 7.0;
+const LANCZOS_COEFFICIENTS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+
+/// Whether `z` is one of the poles of the gamma function.
+pub fn is_gamma_pole(z: Complex64) -> bool {
+    z.im == 0.0 && z.re <= 0.0 && z.re.fract() == 0.0
+}
+
+/// Computes the gamma function with the Lanczos approximation,
+/// reflecting arguments left of 1/2 so the series stays accurate.
+pub fn gamma(z: Complex64) -> Complex64 {
+    if z.re < 0.5 {
+        return PI / ((PI * z).sin() * gamma(1.0 - z));
+    }
+    if z.re > 171.0 {
+        // `t.powc` overflows before the `exp` term can shrink it again,
+        // and real arguments should overflow to a real infinity.
+        let ln = ln_gamma(z);
+        if z.im == 0.0 {
+            return Complex64::new(ln.re.exp(), 0.0);
+        }
+        return ln.exp();
+    }
+    let (t, series) = lanczos_series(z - 1.0);
+    (2.0 * PI).sqrt() * t.powc(z - 0.5) * (-t).exp() * series
+}
+
+/// Computes the natural logarithm of the gamma function without
+/// overflowing for large arguments.
+pub fn ln_gamma(z: Complex64) -> Complex64 {
+    if z.re < 0.5 {
+        return PI.ln() - (PI * z).sin().ln() - ln_gamma(1.0 - z);
+    }
+    let (t, series) = lanczos_series(z - 1.0);
+    0.5 * (2.0 * PI).ln() + (z - 0.5) * t.ln() - t + series.ln()
+}
+
+fn lanczos_series(z: Complex64) -> (Complex64, Complex64) {
+    let mut series = Complex64::new(LANCZOS_COEFFICIENTS[0], 0.0);
+    for (index, coefficient) in LANCZOS_COEFFICIENTS.iter().enumerate().skip(1) {
+        series += coefficient / (z + index as f64);
+    }
+    (z + LANCZOS_G + 0.5, series)
+}
+
+/// Computes `z!`, multiplying exactly for small whole numbers and
+/// falling back to `gamma(z + 1)` everywhere else.
+pub fn factorial(z: Complex64) -> Complex64 {
+    if z.im == 0.0 && z.re >= 0.0 && z.re <= 170.0 && z.re.fract() == 0.0 {
+        let product = (2..=z.re as u64).fold(1.0, |product, n| product * n as f64);
+        return Complex64::new(product, 0.0);
+    }
+    gamma(z + 1.0)
+}
+
+/// Computes the digamma function, the logarithmic derivative of gamma.
+///
+/// Arguments are shifted up with `ψ(z) = ψ(z + 1) - 1/z` until the
+/// asymptotic series converges, reflecting those left of 1/2 first.
+pub fn digamma(mut z: Complex64) -> Complex64 {
+    if z.re < 0.5 {
+        return digamma(1.0 - z) - PI / (PI * z).tan();
+    }
+    let mut result = Complex64::new(0.0, 0.0);
+    while z.norm() < 10.0 {
+        result -= 1.0 / z;
+        z += 1.0;
+    }
+    let inverse_square = 1.0 / (z * z);
+    let series = inverse_square
+        * (1.0 / 12.0
+            - inverse_square
+                * (1.0 / 120.0
+                    - inverse_square
+                        * (1.0 / 252.0 - inverse_square * (1.0 / 240.0 - inverse_square / 132.0))));
+    result + z.ln() - 0.5 / z - series
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn real(re: f64) -> Complex64 {
+        Complex64::new(re, 0.0)
+    }
+
+    fn assert_close(actual: Complex64, expected: f64) {
+        let error = (actual - expected).norm() / expected.abs().max(1.0);
+        assert!(error < 1e-12, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn factorials_of_whole_numbers_are_exact() {
+        assert_eq!(factorial(real(0.0)), real(1.0));
+        assert_eq!(factorial(real(1.0)), real(1.0));
+        assert_eq!(factorial(real(5.0)), real(120.0));
+        assert_eq!(factorial(real(20.0)), real(2_432_902_008_176_640_000.0));
+        assert!(factorial(real(170.0)).re.is_finite());
+        assert_close(factorial(real(0.5)), PI.sqrt() / 2.0);
+    }
+
+    #[test]
+    fn gamma_matches_known_values() {
+        assert_close(gamma(real(0.5)), PI.sqrt());
+        assert_close(gamma(real(5.0)), 24.0);
+        assert_close(gamma(real(-0.5)), -2.0 * PI.sqrt());
+        assert_close(gamma(real(1.0)), 1.0);
+    }
+
+    #[test]
+    fn poles_are_the_non_positive_integers() {
+        for pole in [0.0, -1.0, -2.0, -50.0] {
+            assert!(is_gamma_pole(real(pole)), "{} is a pole", pole);
+        }
+        for regular in [0.5, -0.5, 1.0, 3.0] {
+            assert!(!is_gamma_pole(real(regular)), "{} is not a pole", regular);
+        }
+        assert!(!is_gamma_pole(Complex64::new(-1.0, 1.0)));
+    }
+
+    #[test]
+    fn ln_gamma_does_not_overflow() {
+        assert_close(ln_gamma(real(1000.0)), 5_905.220_423_209_181);
+        assert_close(ln_gamma(real(1e6)), 12_815_504.569_147_612);
+        assert_close(ln_gamma(real(10.0)), 362_880f64.ln());
+        assert!(gamma(real(1000.0)).re.is_infinite());
+    }
+
+    #[test]
+    fn digamma_matches_known_values() {
+        const EULER_MASCHERONI: f64 = 0.577_215_664_901_532_9;
+        assert_close(digamma(real(1.0)), -EULER_MASCHERONI);
+        assert_close(digamma(real(0.5)), -EULER_MASCHERONI - 2.0 * 2f64.ln());
+    }
+}
@@ -2,24 +2,47 @@ use std::cmp::PartialEq;
 use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
 use num::complex::{Complex64, ComplexFloat};
 use num::traits::real::Real;
+use num::Zero;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Write};
 use crate::math::context::Context;
+use crate::math::special;
+
+/// The error for dividing by zero, which graphs skip over as a gap.
+pub const DIVISION_BY_ZERO: &str = "division by zero";
 
 #[derive(Clone, Debug)]
 #[non_exhaustive]
 pub enum Value {
     Number(Complex64),
     Vector(Vec<Value>),
-    Lambda(String, Expression),
+    Lambda(Box<Function>),
     Boolean(bool),
     Error(String),
 }
 
+/// A function value, along with the arguments it has been partially applied to.
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub parameters: Vec<String>,
+    pub body: Expression,
+    /// Values of the leading parameters, bound by calls with too few arguments.
+    pub arguments: Vec<Value>,
+    /// Variables the body read where the function was defined. Names that did not
+    /// exist yet are looked up when it is called.
+    pub captured: HashMap<String, Value>,
+    /// The name the function was first stored under, which its body can
+    /// use to call itself wherever the function ends up.
+    pub name: Option<String>,
+}
+
 impl Display for Value {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Value::Number(value) => {
-                f.write_fmt(format_args!("{}", value))?;
+                // Adding zero turns a `-0` imaginary part, left by negating a real
+                // result, into `0`, so it is not printed as `+-0i`.
+                f.write_fmt(format_args!("{}", Complex64::new(value.re, value.im + 0.0)))?;
                 Ok(())
             }
             Value::Vector(values) => {
@@ -38,9 +61,9 @@ impl Display for Value {
                 f.write_char('"')?;
                 Ok(())
             }
-            Value::Lambda(var, expr) => {
+            Value::Lambda(function) => {
                 f.write_str("fn(")?;
-                f.write_str(var)?;
+                f.write_str(&function.parameters[function.arguments.len()..].join(", "))?;
                 f.write_str("): <...>")?;
                 Ok(())
             }
@@ -57,25 +80,41 @@ impl Display for Value {
 }
 
 impl Value {
+    /// Whether the value is an error, or is a vector holding one at any depth.
+    pub fn has_error(&self) -> bool {
+        match self {
+            Value::Error(_) => true,
+            Value::Vector(values) => values.iter().any(Value::has_error),
+            _ => false,
+        }
+    }
+
     pub fn bin_op(op: BinaryOperation, lhs: &Value, rhs: &Value, ctx: &mut Context) -> Value {
+        // Pass errors on as they are, so one from deep inside a recursion is not
+        // wrapped again at every level on its way out.
+        match (lhs, rhs) {
+            (Value::Error(_), _) if op != BinaryOperation::Store => return lhs.clone(),
+            (_, Value::Error(_)) => return rhs.clone(),
+            _ => {}
+        }
         match op {
             BinaryOperation::Add => Value::add(lhs, rhs),
             BinaryOperation::Sub => Value::sub(lhs, rhs),
             BinaryOperation::Multiply => Value::mul(lhs, rhs),
             BinaryOperation::Divide => Value::div(lhs, rhs),
+            BinaryOperation::Modulo => Value::modulo(lhs, rhs),
+            BinaryOperation::IntegerDivide => Value::int_div(lhs, rhs),
             BinaryOperation::Power => Value::pow(lhs, rhs),
             BinaryOperation::Root => Value::root(lhs, rhs),
+            BinaryOperation::Log => Value::log(lhs, rhs),
+            BinaryOperation::Beta => Value::beta(lhs, rhs),
+            BinaryOperation::LinearSolve => Value::solve_linear(lhs, rhs),
+            BinaryOperation::Dot => Value::dot(lhs, rhs),
+            BinaryOperation::Cross => Value::cross(lhs, rhs),
+            BinaryOperation::Angle => Value::angle(lhs, rhs),
+            BinaryOperation::Project => Value::project(lhs, rhs),
             BinaryOperation::Store => rhs.clone(),
-            BinaryOperation::Invoke => {
-                let Value::Lambda(lambda_var, lambda_expr) = lhs else {
-                    return Value::Error("left-side must be a function".to_string());
-                };
-                ctx.push_frame();
-                ctx.set_variable(lambda_var.clone(), rhs.clone());
-                let result = lambda_expr.eval(ctx);
-                ctx.pop_frame();
-                result
-            }
+            BinaryOperation::Invoke => lhs.call(vec![rhs.clone()], ctx),
             BinaryOperation::GreaterThan => Value::compare(lhs, rhs, ComparisonResult::GreaterThan),
             BinaryOperation::LessThan => Value::compare(lhs, rhs, ComparisonResult::LessThan),
             BinaryOperation::GreaterThanOrEqual => Value::compare(lhs, rhs, ComparisonResult::GreaterThanOrEqual),
@@ -84,9 +123,73 @@ impl Value {
         }
     }
 
+    /// Calls a function with the given arguments, one parameter at a time.
+    ///
+    /// With fewer arguments than parameters the result is a function of the
+    /// remaining ones. Arguments left over once every parameter is bound are
+    /// passed on to the result, which then has to be a function itself.
+    pub fn call(&self, arguments: Vec<Value>, ctx: &mut Context) -> Value {
+        let given = arguments.len();
+        let mut arguments = arguments.into_iter();
+        let mut function = self.clone();
+        let mut taken = 0;
+        loop {
+            let mut lambda = match function {
+                Value::Lambda(lambda) => lambda,
+                Value::Error(_) => return function,
+                _ if taken == 0 => return Value::Error("left-side must be a function".to_string()),
+                _ => {
+                    return Value::Error(format!(
+                        "function takes {} argument{} but {} were given",
+                        taken,
+                        if taken == 1 { "" } else { "s" },
+                        given
+                    ))
+                }
+            };
+            let missing = lambda.parameters.len() - lambda.arguments.len();
+            let before = lambda.arguments.len();
+            lambda.arguments.extend(arguments.by_ref().take(missing));
+            taken += lambda.arguments.len() - before;
+            if lambda.arguments.len() < lambda.parameters.len() {
+                return Value::Lambda(lambda);
+            }
+
+            if let Err(err) = ctx.enter_call() {
+                return err;
+            }
+            ctx.push_frame();
+            if let Some(name) = &lambda.name {
+                let itself = Function {
+                    arguments: vec![],
+                    ..(*lambda).clone()
+                };
+                ctx.set_variable(name.clone(), Value::Lambda(Box::new(itself)));
+            }
+            for (name, value) in lambda.captured {
+                ctx.set_variable(name, value);
+            }
+            for (parameter, argument) in lambda.parameters.iter().zip(lambda.arguments) {
+                ctx.set_variable(parameter.clone(), argument);
+            }
+            function = lambda.body.eval(ctx);
+            ctx.pop_frame();
+            ctx.leave_call();
+            if arguments.len() == 0 {
+                return function;
+            }
+        }
+    }
+
     pub fn unary_op(op: UnaryOperation, value: &Value) -> Value {
         match op {
-            UnaryOperation::Negate => Value::mul(value, &Value::Number(Complex64::new(-1.0, -1.0))),
+            UnaryOperation::Negate => match value {
+                Value::Vector(values) => {
+                    Value::Vector(values.iter().map(|value| Value::unary_op(UnaryOperation::Negate, value)).collect())
+                }
+                Value::Error(_) => value.clone(),
+                _ => Value::mul(value, &Value::Number(Complex64::new(-1.0, 0.0))),
+            },
             UnaryOperation::Sin => value.sin(),
             UnaryOperation::Cos => value.cos(),
             UnaryOperation::Tan => value.tan(),
@@ -99,6 +202,32 @@ impl Value {
             UnaryOperation::InverseHyperbolicSin => value.asinh(),
             UnaryOperation::InverseHyperbolicCos => value.acosh(),
             UnaryOperation::InverseHyperbolicTan => value.atanh(),
+            UnaryOperation::NaturalLog => value.ln(),
+            UnaryOperation::Log10 => value.log10(),
+            UnaryOperation::Exp => value.exp(),
+            UnaryOperation::SquareRoot => value.sqrt(),
+            UnaryOperation::Abs => value.abs(),
+            UnaryOperation::Round => value.round(),
+            UnaryOperation::Floor => value.floor(),
+            UnaryOperation::Ceil => value.ceil(),
+            UnaryOperation::Sign => value.sign(),
+            UnaryOperation::Fractional => value.frac(),
+            UnaryOperation::Factorial => value.factorial(),
+            UnaryOperation::Gamma => value.gamma(),
+            UnaryOperation::LogGamma => value.lgamma(),
+            UnaryOperation::Digamma => value.digamma(),
+            UnaryOperation::Transpose => value.transpose(),
+            UnaryOperation::Determinant => value.determinant(),
+            UnaryOperation::Inverse => value.inverse(),
+            UnaryOperation::Rank => value.rank(),
+            UnaryOperation::Trace => value.trace(),
+            UnaryOperation::LowerUpper => value.lower_upper(),
+            UnaryOperation::QR => value.qr(),
+            UnaryOperation::Eigen => value.eigen(),
+            UnaryOperation::SingularValues => value.singular_values(),
+            UnaryOperation::MatrixExp => value.matrix_exponential(),
+            UnaryOperation::Norm => value.norm(),
+            UnaryOperation::Unit => value.unit(),
         }
     }
 }
@@ -126,10 +255,10 @@ impl Value {
                         expected == ComparisonResult::LessThan
                         || expected == ComparisonResult::LessThanOrEqual);
                 }
-                return Value::Boolean(
+                Value::Boolean(
                     expected == ComparisonResult::Equal
                         || expected == ComparisonResult::GreaterThanOrEqual
-                        || expected == ComparisonResult::LessThanOrEqual);
+                        || expected == ComparisonResult::LessThanOrEqual)
             }
             (_, _) => Value::Error("invalid comparison operands".to_string())
         }
@@ -176,6 +305,9 @@ impl Value {
     pub fn mul(lhs: &Value, rhs: &Value) -> Value {
         match (lhs, rhs) {
             (Value::Number(ln), Value::Number(rn)) => Value::Number(ln * rn),
+            (Value::Vector(_), Value::Vector(_)) if lhs.is_matrix() || rhs.is_matrix() => {
+                Value::matrix_product(lhs, rhs)
+            }
             (Value::Vector(lhsv), Value::Vector(rhsv)) => {
                 if lhsv.len() != rhsv.len() {
                     return Value::Error(
@@ -189,12 +321,16 @@ impl Value {
                         .collect(),
                 )
             }
+            // A number scales every entry of a vector, or of a matrix.
+            (Value::Number(_), Value::Vector(rhsv)) => Value::Vector(rhsv.iter().map(|y| Value::mul(lhs, y)).collect()),
+            (Value::Vector(lhsv), Value::Number(_)) => Value::Vector(lhsv.iter().map(|x| Value::mul(x, rhs)).collect()),
             (lhs, rhs) => Value::Error(format!("Operation on {} and {} not supported", lhs, rhs)),
         }
     }
 
     pub fn div(lhs: &Value, rhs: &Value) -> Value {
         match (lhs, rhs) {
+            (Value::Number(_), Value::Number(rn)) if rn.is_zero() => Value::Error(DIVISION_BY_ZERO.to_string()),
             (Value::Number(ln), Value::Number(rn)) => Value::Number(ln / rn),
             (Value::Vector(lhsv), Value::Vector(rhsv)) => {
                 if lhsv.len() != rhsv.len() {
@@ -207,6 +343,55 @@ impl Value {
                         .collect(),
                 )
             }
+            (Value::Number(_), Value::Vector(rhsv)) => Value::Vector(rhsv.iter().map(|y| Value::div(lhs, y)).collect()),
+            (Value::Vector(lhsv), Value::Number(_)) => Value::Vector(lhsv.iter().map(|x| Value::div(x, rhs)).collect()),
+            (lhs, rhs) => Value::Error(format!("Operation on {} and {} not supported", lhs, rhs)),
+        }
+    }
+
+    /// Divides and floors the quotient, treating the real and imaginary parts separately.
+    pub fn int_div(lhs: &Value, rhs: &Value) -> Value {
+        match (lhs, rhs) {
+            (Value::Number(_), Value::Number(rn)) if rn.is_zero() => Value::Error(DIVISION_BY_ZERO.to_string()),
+            (Value::Number(ln), Value::Number(rn)) => {
+                let quotient = ln / rn;
+                Value::Number(Complex64::new(quotient.re.floor(), quotient.im.floor()))
+            }
+            (Value::Vector(lhsv), Value::Vector(rhsv)) => {
+                if lhsv.len() != rhsv.len() {
+                    return Value::Error("vectors must have same length to be divided".to_string());
+                }
+                Value::Vector(
+                    lhsv.iter()
+                        .zip(rhsv.iter())
+                        .map(|(x, y)| Value::int_div(x, y))
+                        .collect(),
+                )
+            }
+            (lhs, rhs) => Value::Error(format!("Operation on {} and {} not supported", lhs, rhs)),
+        }
+    }
+
+    /// The remainder left by `int_div`, which takes the sign of the divisor for real numbers.
+    pub fn modulo(lhs: &Value, rhs: &Value) -> Value {
+        match (lhs, rhs) {
+            (Value::Number(_), Value::Number(rn)) if rn.is_zero() => Value::Error(DIVISION_BY_ZERO.to_string()),
+            (Value::Number(ln), Value::Number(rn)) => {
+                let quotient = ln / rn;
+                let quotient = Complex64::new(quotient.re.floor(), quotient.im.floor());
+                Value::Number(ln - rn * quotient)
+            }
+            (Value::Vector(lhsv), Value::Vector(rhsv)) => {
+                if lhsv.len() != rhsv.len() {
+                    return Value::Error("vectors must have same length to be divided".to_string());
+                }
+                Value::Vector(
+                    lhsv.iter()
+                        .zip(rhsv.iter())
+                        .map(|(x, y)| Value::modulo(x, y))
+                        .collect(),
+                )
+            }
             (lhs, rhs) => Value::Error(format!("Operation on {} and {} not supported", lhs, rhs)),
         }
     }
@@ -214,6 +399,7 @@ impl Value {
     pub fn pow(lhs: &Value, rhs: &Value) -> Value {
         match (lhs, rhs) {
             (Value::Number(ln), Value::Number(rn)) => Value::Number(ln.powc(*rn)),
+            (Value::Vector(_), Value::Number(rn)) if lhs.is_matrix() => lhs.matrix_power(rn),
             (lhs, rhs) => Value::Error(format!("Operation on {} and {} not supported", lhs, rhs)),
         }
     }
@@ -225,11 +411,40 @@ impl Value {
         }
     }
 
+    /// Computes the logarithm of `rhs` in base `lhs`.
+    pub fn log(lhs: &Value, rhs: &Value) -> Value {
+        match (lhs, rhs) {
+            (Value::Number(ln), Value::Number(rn)) if is_positive_real(ln) && is_positive_real(rn) => {
+                Value::Number(Complex64::new(real_log(ln.re, rn.re), 0.0))
+            }
+            (Value::Number(ln), Value::Number(rn)) => Value::Number(rn.ln() / ln.ln()),
+            (Value::Number(_), Value::Vector(rhsv)) => {
+                Value::Vector(rhsv.iter().map(|x| Value::log(lhs, x)).collect())
+            }
+            (lhs, rhs) => Value::Error(format!("Operation on {} and {} not supported", lhs, rhs)),
+        }
+    }
+
+    /// Computes the beta function `Γ(a)Γ(b) / Γ(a + b)`.
+    pub fn beta(lhs: &Value, rhs: &Value) -> Value {
+        match (lhs, rhs) {
+            (Value::Number(ln), Value::Number(rn)) => {
+                if special::is_gamma_pole(*ln) || special::is_gamma_pole(*rn) {
+                    return Value::Error("beta is undefined at non-positive integers".to_string());
+                }
+                Value::Number(
+                    (special::ln_gamma(*ln) + special::ln_gamma(*rn) - special::ln_gamma(ln + rn)).exp(),
+                )
+            }
+            (lhs, rhs) => Value::Error(format!("Operation on {} and {} not supported", lhs, rhs)),
+        }
+    }
+
     pub fn sin(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.sin()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::sin(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::sin).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -238,8 +453,8 @@ impl Value {
     pub fn cos(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.cos()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::cos(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::cos).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -248,8 +463,8 @@ impl Value {
     pub fn tan(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.tan()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::tan(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::tan).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -258,8 +473,8 @@ impl Value {
     pub fn asin(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.asin()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::asin(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::asin).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -268,8 +483,8 @@ impl Value {
     pub fn acos(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.acos()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::acos(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::acos).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -278,8 +493,8 @@ impl Value {
     pub fn atan(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.atan()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::atan(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::atan).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -288,8 +503,8 @@ impl Value {
     pub fn sinh(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.sinh()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::sin(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::sin).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -298,8 +513,8 @@ impl Value {
     pub fn cosh(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.cosh()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::cosh(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::cosh).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -308,8 +523,8 @@ impl Value {
     pub fn tanh(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.tanh()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::tanh(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::tanh).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -318,8 +533,8 @@ impl Value {
     pub fn asinh(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.asinh()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::asinh(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::asinh).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -328,8 +543,8 @@ impl Value {
     pub fn acosh(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.acosh()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::acosh(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::acosh).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -338,8 +553,49 @@ impl Value {
     pub fn atanh(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(num.atanh()),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::atanh(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::atanh).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn ln(&self) -> Value {
+        match &self {
+            Value::Number(num) => Value::Number(num.ln()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::ln).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn log10(&self) -> Value {
+        match &self {
+            Value::Number(num) if is_positive_real(num) => Value::Number(Complex64::new(real_log(10.0, num.re), 0.0)),
+            Value::Number(num) => Value::Number(num.log10()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::log10).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn exp(&self) -> Value {
+        match &self {
+            Value::Number(num) => Value::Number(num.exp()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::exp).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn sqrt(&self) -> Value {
+        match &self {
+            Value::Number(num) => Value::Number(num.sqrt()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::sqrt).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -348,8 +604,8 @@ impl Value {
     pub fn abs(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(Complex64::new(num.abs(), 0.0)),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::abs(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::abs).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
@@ -358,10 +614,176 @@ impl Value {
     pub fn round(&self) -> Value {
         match &self {
             Value::Number(num) => Value::Number(Complex64::new(num.re.round(), num.im.round())),
-            Value::Vector(vals) => Value::Vector(vals.iter().map(|x| Value::round(x)).collect()),
-            Value::Lambda(var, expr) => Value::Error("WIP".to_string()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::round).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn floor(&self) -> Value {
+        match &self {
+            Value::Number(num) => Value::Number(Complex64::new(num.re.floor(), num.im.floor())),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::floor).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn ceil(&self) -> Value {
+        match &self {
+            Value::Number(num) => Value::Number(Complex64::new(num.re.ceil(), num.im.ceil())),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::ceil).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn frac(&self) -> Value {
+        match &self {
+            Value::Number(num) => Value::Number(Complex64::new(num.re.fract(), num.im.fract())),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::frac).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    /// The number divided by its magnitude, so real numbers map to -1, 0 or 1.
+    pub fn sign(&self) -> Value {
+        match &self {
+            Value::Number(num) if num.norm() == 0.0 => Value::Number(Complex64::new(0.0, 0.0)),
+            Value::Number(num) => Value::Number(num / num.norm()),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::sign).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn factorial(&self) -> Value {
+        match &self {
+            Value::Number(num) if special::is_gamma_pole(num + 1.0) => {
+                Value::Error("factorial is undefined for negative integers".to_string())
+            }
+            Value::Number(num) => Value::Number(special::factorial(*num)),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::factorial).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn gamma(&self) -> Value {
+        match &self {
+            Value::Number(num) if special::is_gamma_pole(*num) => {
+                Value::Error("gamma is undefined at non-positive integers".to_string())
+            }
+            Value::Number(num) => Value::Number(special::gamma(*num)),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::gamma).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+
+    pub fn lgamma(&self) -> Value {
+        match &self {
+            Value::Number(num) if special::is_gamma_pole(*num) => {
+                Value::Error("lgamma is undefined at non-positive integers".to_string())
+            }
+            Value::Number(num) => Value::Number(special::ln_gamma(*num)),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::lgamma).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
             Value::Error(_err) => self.clone(),
             _ => Value::Error("invalid operand".to_string())
         }
     }
+
+    pub fn digamma(&self) -> Value {
+        match &self {
+            Value::Number(num) if special::is_gamma_pole(*num) => {
+                Value::Error("digamma is undefined at non-positive integers".to_string())
+            }
+            Value::Number(num) => Value::Number(special::digamma(*num)),
+            Value::Vector(vals) => Value::Vector(vals.iter().map(Value::digamma).collect()),
+            Value::Lambda(_) => Value::Error("WIP".to_string()),
+            Value::Error(_err) => self.clone(),
+            _ => Value::Error("invalid operand".to_string())
+        }
+    }
+}
+
+fn is_positive_real(number: &Complex64) -> bool {
+    number.im == 0.0 && number.re > 0.0
+}
+
+/// The logarithm of a positive real in a positive real base, which is exact
+/// when the real is a whole power of the base, like `log10(1000)`.
+fn real_log(base: f64, x: f64) -> f64 {
+    let result = if base == 2.0 {
+        x.log2()
+    } else if base == 10.0 {
+        x.log10()
+    } else {
+        x.log(base)
+    };
+    let whole = result.round();
+    if whole != result && base.powf(whole) == x {
+        whole
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(value: f64) -> Value {
+        Value::Number(Complex64::new(value, 0.0))
+    }
+
+    #[test]
+    fn integer_division_and_remainder() {
+        assert_eq!(Value::int_div(&number(7.0), &number(2.0)).to_string(), "3+0i");
+        assert_eq!(Value::modulo(&number(-7.0), &number(3.0)).to_string(), "2+0i");
+    }
+
+    #[test]
+    fn integer_division_by_zero_is_an_error() {
+        assert_eq!(Value::int_div(&number(5.0), &number(0.0)).to_string(), "error: \"division by zero\"");
+        assert_eq!(Value::modulo(&number(5.0), &number(0.0)).to_string(), "error: \"division by zero\"");
+    }
+
+    #[test]
+    fn division_by_zero_is_an_error_in_every_entry() {
+        let vector = Value::Vector(vec![number(1.0), number(2.0)]);
+        let error = Value::div(&vector, &number(0.0));
+        assert_eq!(error.to_string(), "<error: \"division by zero\",  error: \"division by zero\",  >");
+        assert!(error.has_error());
+        assert!(Value::Vector(vec![Value::Vector(vec![number(1.0), error])]).has_error());
+        assert!(!Value::div(&vector, &number(2.0)).has_error());
+    }
+
+    #[test]
+    fn logarithms_of_whole_powers_are_exact() {
+        assert_eq!(number(1000.0).log10().to_string(), "3+0i");
+        assert_eq!(number(1e-3).log10().to_string(), "-3+0i");
+        assert_eq!(Value::log(&number(2.0), &number(8.0)).to_string(), "3+0i");
+        assert_eq!(Value::log(&number(3.0), &number(243.0)).to_string(), "5+0i");
+        assert_eq!(Value::log(&number(10.0), &number(1e15)).to_string(), "15+0i");
+        assert_eq!(Value::log(&number(2.0), &number(0.125)).to_string(), "-3+0i");
+        assert_eq!(number(-100.0).log10().to_string(), "2+1.3643763538418412i");
+    }
+
+    #[test]
+    fn negative_zero_imaginary_parts_print_as_zero() {
+        assert_eq!(Value::Number(Complex64::new(-2.0, -0.0)).to_string(), "-2+0i");
+        assert_eq!(Value::Number(Complex64::new(1.0, -1.0)).to_string(), "1-1i");
+        let determinant = Expression::parse("det([[1, 2], [3, 4]])").unwrap().eval(&mut Context::default());
+        assert_eq!(determinant.to_string(), "-2+0i");
+    }
 }
@@ -0,0 +1,85 @@
+use comp_rs::math::context::{Context, Frame, GLOBAL_MATH_CONTEXT};
+use comp_rs::math::expr::Expression;
+use comp_rs::math::values::Value;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::process::ExitCode;
+
+const HELP: &str = "\
+Enter an expression to evaluate it, e.g. `a = 3` then `a * 2`.
+Variables starting with `@` are global.
+  :vars   list every variable in scope
+  :clear  forget all variables
+  :help   show this message
+  :quit   exit the REPL";
+
+/// Runs an interactive prompt that keeps one context alive across lines.
+pub fn run() -> ExitCode {
+    let mut editor = match DefaultEditor::new() {
+        Ok(editor) => editor,
+        Err(err) => {
+            eprintln!("unable to start the REPL: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut ctx = Context::default();
+
+    println!("comp-rs REPL, type :help for commands");
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                eprintln!("{}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+
+        match line {
+            ":vars" => print_variables(&ctx),
+            ":clear" => {
+                ctx = Context::default();
+                *GLOBAL_MATH_CONTEXT.lock() = Context::default();
+            }
+            ":help" => println!("{}", HELP),
+            ":quit" | ":q" => break,
+            _ if line.starts_with(':') => println!("unknown command `{}`, type :help for commands", line),
+            _ => {
+                let result = match Expression::parse(line) {
+                    Ok(expr) => {
+                        ctx.reset_steps();
+                        expr.eval(&mut ctx)
+                    }
+                    Err(err) => Value::Error(err),
+                };
+                println!("{}", result);
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn print_variables(ctx: &Context) {
+    for (index, frame) in ctx.frames.iter().enumerate() {
+        println!("frame {}:", index);
+        print_frame(frame);
+    }
+    println!("global:");
+    for frame in &GLOBAL_MATH_CONTEXT.lock().frames {
+        print_frame(frame);
+    }
+}
+
+fn print_frame(frame: &Frame) {
+    let mut names: Vec<&String> = frame.variables.keys().collect();
+    names.sort();
+    for name in names {
+        println!("  {} = {}", name, frame.variables[name]);
+    }
+}
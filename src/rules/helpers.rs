@@ -1,5 +1,6 @@
-use crate::gui::idx::new_id;
+use crate::math::idx::new_id;
 use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
+use crate::rules::ooo::Precedence;
 
 impl Expression {
     pub fn build_binop(&mut self, op: BinaryOperation, pat: &str) {
@@ -23,6 +24,39 @@ impl Expression {
         }
     }
 
+    pub fn build_bracketed(&mut self, op: BinaryOperation) {
+        *self = Expression::Binary {
+            op,
+            lhs: Box::new(Expression::Literal {
+                content: "?".to_string(),
+                id: new_id(),
+                new_literal: true,
+            }),
+            rhs: Box::new(Expression::Literal {
+                content: "?".to_string(),
+                id: new_id(),
+                new_literal: false,
+            }),
+            id: new_id(),
+        }
+    }
+
+    pub fn build_postfix(&mut self, op: UnaryOperation, pat: &str) {
+        let Expression::Literal { content, .. } = self else {
+            panic!("can not build node out of a non-literal")
+        };
+
+        *self = Expression::Unary {
+            operation: op,
+            expr: Box::new(Expression::Literal {
+                content: content.replace(pat, ""),
+                id: new_id(),
+                new_literal: false,
+            }),
+            id: new_id(),
+        };
+    }
+
     pub fn build_unop(&mut self, op: UnaryOperation) {
         *self = Expression::Unary {
             operation: op,
@@ -34,4 +68,137 @@ impl Expression {
             id: new_id(),
         };
     }
+
+    /// Builds a literal holding `value`, negating it with a unary node so the
+    /// literal itself stays a plain number.
+    pub fn number(value: f64) -> Expression {
+        if value < 0.0 {
+            return Expression::unary(UnaryOperation::Negate, Expression::number(-value));
+        }
+        Expression::variable(&value.to_string())
+    }
+
+    pub fn variable(name: &str) -> Expression {
+        Expression::Literal {
+            content: name.to_string(),
+            id: new_id(),
+            new_literal: false,
+        }
+    }
+
+    pub fn unary(operation: UnaryOperation, expr: Expression) -> Expression {
+        Expression::Unary {
+            operation,
+            expr: Box::new(expr),
+            id: new_id(),
+        }
+    }
+
+    /// Builds `lhs op rhs`, parenthesizing operands the same way the printer does
+    /// so that generated trees survive [`Expression::enforce_ooo`] unchanged.
+    pub fn binary(op: BinaryOperation, lhs: Expression, rhs: Expression) -> Expression {
+        if op.is_bracketed() {
+            return Expression::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                id: new_id(),
+            };
+        }
+        Expression::Binary {
+            op,
+            lhs: Box::new(group(lhs, op.precedence(), false)),
+            rhs: Box::new(group(rhs, op.precedence(), true)),
+            id: new_id(),
+        }
+    }
+}
+
+fn group(expr: Expression, precedence: Precedence, right: bool) -> Expression {
+    if !expr.needs_brackets(precedence, right) {
+        return expr;
+    }
+    Expression::Parenthesis {
+        expr: Box::new(expr),
+        id: new_id(),
+        unbox_to_binop: false,
+    }
+}
+
+impl Expression {
+    /// Gives every node a fresh id, so trees assembled from copies of the
+    /// same subtree do not share widgets in the editor.
+    pub fn renew_ids(&mut self) {
+        match self {
+            Expression::Unary { expr, id, .. } | Expression::Parenthesis { expr, id, .. } => {
+                *id = new_id();
+                expr.renew_ids();
+            }
+            Expression::Binary { lhs, rhs, id, .. } => {
+                *id = new_id();
+                lhs.renew_ids();
+                rhs.renew_ids();
+            }
+            Expression::Vector { exprs, id } => {
+                *id = new_id();
+                for expr in exprs {
+                    expr.renew_ids();
+                }
+            }
+            Expression::Literal { id, .. } => *id = new_id(),
+            Expression::GraphExpression { expr } => expr.renew_ids(),
+            Expression::Summation {
+                minimum,
+                maximum,
+                variable,
+                expression,
+            }
+            | Expression::Product {
+                minimum,
+                maximum,
+                variable,
+                expression,
+            }
+            | Expression::Solve {
+                minimum,
+                maximum,
+                variable,
+                equation: expression,
+            }
+            | Expression::Integral {
+                lower: minimum,
+                upper: maximum,
+                variable,
+                integrand: expression,
+            } => {
+                minimum.renew_ids();
+                maximum.renew_ids();
+                variable.renew_ids();
+                expression.renew_ids();
+            }
+            Expression::Derivative { variable, expression }
+            | Expression::Roots {
+                polynomial: expression,
+                variable,
+            } => {
+                variable.renew_ids();
+                expression.renew_ids();
+            }
+            Expression::Conditional {
+                condition,
+                consequent,
+                alternative,
+            } => {
+                condition.renew_ids();
+                consequent.renew_ids();
+                alternative.renew_ids();
+            }
+            Expression::Lambda { variables, expr } => {
+                for variable in variables {
+                    variable.renew_ids();
+                }
+                expr.renew_ids();
+            }
+        }
+    }
 }
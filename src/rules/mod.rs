@@ -1,3 +1,9 @@
 mod helpers;
 mod ooo;
 mod update;
+mod parse;
+mod polynomial;
+mod print;
+mod simplify;
+
+pub use ooo::Precedence;
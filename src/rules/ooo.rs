@@ -1,5 +1,5 @@
-use crate::gui::idx::new_id;
-use crate::math::expr::{BinaryOperation, Expression};
+use crate::math::idx::new_id;
+use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
 use std::cmp::PartialOrd;
 
 #[repr(u8)]
@@ -7,7 +7,6 @@ use std::cmp::PartialOrd;
 pub enum Precedence {
     Value = 0,
     Parenthesis,
-    Storage,
     Unary,
     Exponent,
     Factor,
@@ -15,34 +14,99 @@ pub enum Precedence {
     Comparison,
     And,
     Or,
+    Storage,
     Summation,
     Lambda
 }
 
+impl BinaryOperation {
+    pub fn precedence(&self) -> Precedence {
+        match self {
+            BinaryOperation::Add => Precedence::Term,
+            BinaryOperation::Sub => Precedence::Term,
+            BinaryOperation::Multiply => Precedence::Factor,
+            BinaryOperation::Divide => Precedence::Factor,
+            BinaryOperation::Modulo => Precedence::Factor,
+            BinaryOperation::IntegerDivide => Precedence::Factor,
+            BinaryOperation::Power => Precedence::Exponent,
+            BinaryOperation::Root => Precedence::Exponent,
+            BinaryOperation::Log => Precedence::Lambda,
+            BinaryOperation::Beta => Precedence::Lambda,
+            BinaryOperation::LinearSolve => Precedence::Lambda,
+            BinaryOperation::Dot => Precedence::Lambda,
+            BinaryOperation::Cross => Precedence::Lambda,
+            BinaryOperation::Angle => Precedence::Lambda,
+            BinaryOperation::Project => Precedence::Lambda,
+            BinaryOperation::Invoke => Precedence::Lambda,
+            BinaryOperation::Store => Precedence::Storage,
+            BinaryOperation::GreaterThan => Precedence::Comparison,
+            BinaryOperation::LessThan => Precedence::Comparison,
+            BinaryOperation::GreaterThanOrEqual => Precedence::Comparison,
+            BinaryOperation::LessThanOrEqual => Precedence::Comparison,
+            BinaryOperation::Equal => Precedence::Comparison,
+        }
+    }
+
+    /// Whether the right side of this operation is enclosed in brackets, like a call's argument.
+    pub fn is_bracketed(&self) -> bool {
+        matches!(
+            self,
+            BinaryOperation::Invoke
+                | BinaryOperation::Log
+                | BinaryOperation::Beta
+                | BinaryOperation::LinearSolve
+                | BinaryOperation::Dot
+                | BinaryOperation::Cross
+                | BinaryOperation::Angle
+                | BinaryOperation::Project
+        )
+    }
+
+    /// Whether `a op b op c` groups as `a op (b op c)`, as powers, roots and
+    /// assignments do.
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self.precedence(), Precedence::Exponent | Precedence::Storage)
+    }
+
+    /// Whether the operation is written as a function of its two sides, like `beta(a, b)`.
+    pub fn is_function(&self) -> bool {
+        self.is_bracketed() && !matches!(self, BinaryOperation::Invoke | BinaryOperation::Log)
+    }
+}
+
 impl Expression {
+    /// Whether the expression needs brackets to be read back as the left or
+    /// right operand of an operation that binds at `precedence`.
+    pub fn needs_brackets(&self, precedence: Precedence, right: bool) -> bool {
+        // Operators of the same precedence need no brackets on the side they associate to.
+        let associates = right == matches!(precedence, Precedence::Exponent | Precedence::Storage);
+        match self {
+            Expression::Lambda { .. } => true,
+            Expression::Binary { op, .. } if op.is_bracketed() => false,
+            // `-x^2` is `-(x^2)`, so a negated base keeps its brackets.
+            Expression::Unary { operation: UnaryOperation::Negate, .. } => {
+                precedence == Precedence::Exponent && !right
+            }
+            _ if associates => self.precedence() > precedence,
+            _ => self.precedence() >= precedence,
+        }
+    }
+
     pub fn precedence(&self) -> Precedence {
         match self {
             Expression::Unary { .. } => Precedence::Unary,
-            Expression::Binary { op, .. } => match op {
-                BinaryOperation::Add => Precedence::Term,
-                BinaryOperation::Sub => Precedence::Term,
-                BinaryOperation::Multiply => Precedence::Factor,
-                BinaryOperation::Divide => Precedence::Factor,
-                BinaryOperation::Power => Precedence::Exponent,
-                BinaryOperation::Root => Precedence::Exponent,
-                BinaryOperation::Invoke => Precedence::Lambda,
-                BinaryOperation::Store => Precedence::Storage,
-                BinaryOperation::GreaterThan => Precedence::Comparison,
-                BinaryOperation::LessThan => Precedence::Comparison,
-                BinaryOperation::GreaterThanOrEqual => Precedence::Comparison,
-                BinaryOperation::LessThanOrEqual => Precedence::Comparison,
-                BinaryOperation::Equal => Precedence::Comparison,
-            },
+            Expression::Binary { op, .. } => op.precedence(),
             Expression::Vector { .. } => Precedence::Value,
             Expression::Literal { .. } => Precedence::Value,
             Expression::Parenthesis { .. } => Precedence::Parenthesis,
             Expression::GraphExpression { .. } => Precedence::Parenthesis,
             Expression::Summation { .. } => Precedence::Value,
+            Expression::Product { .. } => Precedence::Value,
+            Expression::Solve { .. } => Precedence::Value,
+            Expression::Integral { .. } => Precedence::Value,
+            Expression::Derivative { .. } => Precedence::Value,
+            Expression::Roots { .. } => Precedence::Value,
+            Expression::Conditional { .. } => Precedence::Value,
             Expression::Lambda { .. } => Precedence::Value,
         }
     }
@@ -72,14 +136,20 @@ impl Expression {
                     return;
                 };
 
-                if rhs_precedence < self_precedence {
+                // A call's argument list is bracketed, so it never gives up its
+                // function to an operator on its left.
+                if rhs_precedence < self_precedence || rhs_op.is_bracketed() {
+                    return;
+                }
+                // `a ^ (b ^ c)` is already grouped the way `a ^ b ^ c` reads, as is `a = (b = c)`.
+                if rhs_precedence == self_precedence && rhs_op.is_right_associative() {
                     return;
                 }
 
                 *self = Expression::Binary {
-                    op: rhs_op.clone(),
+                    op: rhs_op,
                     lhs: Box::new(Expression::Binary {
-                        op: op.clone(),
+                        op: *op,
                         lhs: lhs.clone(),
                         rhs: target_value.clone(),
                         id: new_id(),
@@ -90,11 +160,40 @@ impl Expression {
             }
             Expression::Unary { expr, .. } => expr.enforce_ooo(),
             Expression::GraphExpression { expr } => expr.enforce_ooo(),
+            Expression::Derivative { expression, .. } => expression.enforce_ooo(),
+            Expression::Roots { polynomial, .. } => polynomial.enforce_ooo(),
+            Expression::Conditional {
+                condition,
+                consequent,
+                alternative,
+            } => {
+                condition.enforce_ooo();
+                consequent.enforce_ooo();
+                alternative.enforce_ooo();
+            }
             Expression::Summation {
                 expression,
                 maximum,
                 minimum,
                 ..
+            }
+            | Expression::Product {
+                expression,
+                maximum,
+                minimum,
+                ..
+            }
+            | Expression::Solve {
+                equation: expression,
+                maximum,
+                minimum,
+                ..
+            }
+            | Expression::Integral {
+                integrand: expression,
+                upper: maximum,
+                lower: minimum,
+                ..
             } => {
                 expression.enforce_ooo();
                 maximum.enforce_ooo();
@@ -0,0 +1,635 @@
+use crate::math::idx::new_id;
+use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
+use crate::rules::ooo::Precedence;
+use std::str::FromStr;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(String),
+    Identifier(String),
+    Symbol(&'static str),
+    End,
+}
+
+const SYMBOLS: [&str; 19] = [
+    "==", ">=", "<=", "+", "-", "*", "/", "÷", "^", "√", "=", ">", "<", "(", ")", "[", "]", ",",
+    "!",
+];
+
+const UNARY_FUNCTIONS: [UnaryOperation; 37] = [
+    UnaryOperation::Sin,
+    UnaryOperation::Cos,
+    UnaryOperation::Tan,
+    UnaryOperation::InverseSin,
+    UnaryOperation::InverseCos,
+    UnaryOperation::InverseTan,
+    UnaryOperation::HyperbolicSin,
+    UnaryOperation::HyperbolicCos,
+    UnaryOperation::HyperbolicTan,
+    UnaryOperation::InverseHyperbolicSin,
+    UnaryOperation::InverseHyperbolicCos,
+    UnaryOperation::InverseHyperbolicTan,
+    UnaryOperation::NaturalLog,
+    UnaryOperation::Log10,
+    UnaryOperation::Exp,
+    UnaryOperation::SquareRoot,
+    UnaryOperation::Abs,
+    UnaryOperation::Round,
+    UnaryOperation::Floor,
+    UnaryOperation::Ceil,
+    UnaryOperation::Sign,
+    UnaryOperation::Fractional,
+    UnaryOperation::Gamma,
+    UnaryOperation::LogGamma,
+    UnaryOperation::Digamma,
+    UnaryOperation::Transpose,
+    UnaryOperation::Determinant,
+    UnaryOperation::Inverse,
+    UnaryOperation::Rank,
+    UnaryOperation::Trace,
+    UnaryOperation::LowerUpper,
+    UnaryOperation::QR,
+    UnaryOperation::Eigen,
+    UnaryOperation::SingularValues,
+    UnaryOperation::MatrixExp,
+    UnaryOperation::Norm,
+    UnaryOperation::Unit,
+];
+
+impl UnaryOperation {
+    /// Looks up a function by the name it is typed as, accepting both the
+    /// displayed form (`sin^-1`) and the editor keyword (`asin`).
+    pub fn from_name(name: &str) -> Option<UnaryOperation> {
+        let alias = match name {
+            "asin" => Some(UnaryOperation::InverseSin),
+            "acos" => Some(UnaryOperation::InverseCos),
+            "atan" => Some(UnaryOperation::InverseTan),
+            "asinh" => Some(UnaryOperation::InverseHyperbolicSin),
+            "acosh" => Some(UnaryOperation::InverseHyperbolicCos),
+            "atanh" => Some(UnaryOperation::InverseHyperbolicTan),
+            "log" => Some(UnaryOperation::Log10),
+            _ => None,
+        };
+        alias.or_else(|| {
+            UNARY_FUNCTIONS
+                .iter()
+                .find(|op| op.to_string() == name)
+                .cloned()
+        })
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+        let start = index;
+
+        if c.is_whitespace() {
+            index += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(index + 1).is_some_and(char::is_ascii_digit)) {
+            while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.') {
+                index += 1;
+            }
+            if chars[start..index].iter().filter(|c| **c == '.').count() > 1 {
+                let number: String = chars[start..index].iter().collect();
+                return Err(format!("invalid number `{}` at position {}", number, start));
+            }
+            if index < chars.len() && (chars[index] == 'e' || chars[index] == 'E') {
+                let mut exponent = index + 1;
+                if exponent < chars.len() && (chars[exponent] == '+' || chars[exponent] == '-') {
+                    exponent += 1;
+                }
+                if exponent < chars.len() && chars[exponent].is_ascii_digit() {
+                    index = exponent;
+                    while index < chars.len() && chars[index].is_ascii_digit() {
+                        index += 1;
+                    }
+                }
+            }
+            tokens.push((Token::Number(chars[start..index].iter().collect()), start));
+        } else if c.is_alphabetic() || c == '_' || c == '@' {
+            index += 1;
+            while index < chars.len() && (chars[index].is_alphanumeric() || chars[index] == '_') {
+                index += 1;
+            }
+            let mut name: String = chars[start..index].iter().collect();
+
+            // `sin^-1` is a single function name rather than a power of `sin`.
+            let inverse: String = chars[index..].iter().take(3).collect();
+            if inverse == "^-1" && UnaryOperation::from_name(&format!("{}^-1", name)).is_some() {
+                name.push_str("^-1");
+                index += 3;
+            }
+            tokens.push((Token::Identifier(name), start));
+        } else {
+            let rest: String = chars[index..].iter().take(2).collect();
+            let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) else {
+                return Err(format!("unexpected character `{}` at position {}", c, index));
+            };
+            index += symbol.chars().count();
+            tokens.push((Token::Symbol(symbol), start));
+        }
+    }
+
+    tokens.push((Token::End, chars.len()));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+        if token != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn unexpected(&self) -> String {
+        let (token, position) = &self.tokens[self.position];
+        match token {
+            Token::Number(text) | Token::Identifier(text) => {
+                format!("unexpected `{}` at position {}", text, position)
+            }
+            Token::Symbol(symbol) => format!("unexpected `{}` at position {}", symbol, position),
+            Token::End => "unexpected end of input".to_string(),
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), String> {
+        if *self.peek() != Token::Symbol(symbol) {
+            return Err(format!("expected `{}`, {}", symbol, self.unexpected()));
+        }
+        self.advance();
+        Ok(())
+    }
+
+    fn binary_operation(&self) -> Option<BinaryOperation> {
+        let symbol = match self.peek() {
+            Token::Symbol(symbol) => *symbol,
+            Token::Identifier(name) if name == "mod" => return Some(BinaryOperation::Modulo),
+            Token::Identifier(name) if name == "div" => return Some(BinaryOperation::IntegerDivide),
+            _ => return None,
+        };
+        match symbol {
+            "+" => Some(BinaryOperation::Add),
+            "-" => Some(BinaryOperation::Sub),
+            "*" => Some(BinaryOperation::Multiply),
+            "/" | "÷" => Some(BinaryOperation::Divide),
+            "^" => Some(BinaryOperation::Power),
+            "√" => Some(BinaryOperation::Root),
+            "=" => Some(BinaryOperation::Store),
+            ">" => Some(BinaryOperation::GreaterThan),
+            "<" => Some(BinaryOperation::LessThan),
+            ">=" => Some(BinaryOperation::GreaterThanOrEqual),
+            "<=" => Some(BinaryOperation::LessThanOrEqual),
+            "==" => Some(BinaryOperation::Equal),
+            _ => None,
+        }
+    }
+
+    /// Parses a chain of binary operations that bind at least as tightly as `limit`.
+    /// Powers, roots and assignments associate to the right and every other
+    /// operator to the left, matching the trees `enforce_ooo` produces.
+    fn parse_expression(&mut self, limit: u8) -> Result<Expression, String> {
+        let mut lhs = self.parse_prefix()?;
+
+        while *self.peek() == Token::Symbol("!") {
+            self.advance();
+            lhs = Expression::Unary {
+                operation: UnaryOperation::Factorial,
+                expr: Box::new(lhs),
+                id: new_id(),
+            };
+        }
+
+        while let Some(op) = self.binary_operation() {
+            let precedence = op.precedence() as u8;
+            if precedence > limit {
+                break;
+            }
+            self.advance();
+            let rhs = match op.is_right_associative() {
+                true => self.parse_expression(precedence)?,
+                false => self.parse_expression(precedence - 1)?,
+            };
+            lhs = Expression::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+                id: new_id(),
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_full(&mut self) -> Result<Expression, String> {
+        self.parse_expression(u8::MAX)
+    }
+
+    fn parse_operand(&mut self) -> Result<Expression, String> {
+        self.parse_expression(Precedence::Unary as u8 - 1)
+    }
+
+    fn parse_prefix(&mut self) -> Result<Expression, String> {
+        match self.advance() {
+            Token::Number(content) => Ok(literal(content)),
+            Token::Identifier(name) => self.parse_identifier(name),
+            // Powers bind tighter than negation, so `-x^2` is `-(x^2)`.
+            Token::Symbol("-") => Ok(Expression::Unary {
+                operation: UnaryOperation::Negate,
+                expr: Box::new(self.parse_expression(Precedence::Exponent as u8)?),
+                id: new_id(),
+            }),
+            Token::Symbol("(") => {
+                let expr = self.parse_full()?;
+                self.expect(")")?;
+                let group = Expression::Parenthesis {
+                    expr: Box::new(expr),
+                    id: new_id(),
+                    unbox_to_binop: false,
+                };
+                if *self.peek() == Token::Symbol("(") {
+                    self.advance();
+                    return self.parse_call(group);
+                }
+                Ok(group)
+            }
+            Token::Symbol("[") => {
+                let exprs = self.parse_arguments("]")?;
+                Ok(Expression::Vector { exprs, id: new_id() })
+            }
+            Token::End => Err(self.unexpected()),
+            _ => {
+                // Step back so the error names the token that could not start an operand.
+                self.position -= 1;
+                Err(self.unexpected())
+            }
+        }
+    }
+
+    fn parse_identifier(&mut self, name: String) -> Result<Expression, String> {
+        let is_call = *self.peek() == Token::Symbol("(");
+
+        if let Some(operation) = UnaryOperation::from_name(&name) {
+            let expr = if is_call {
+                self.advance();
+                let expr = self.parse_full()?;
+                self.expect(")")?;
+                expr
+            } else {
+                self.parse_operand()?
+            };
+            return Ok(Expression::Unary {
+                operation,
+                expr: Box::new(expr),
+                id: new_id(),
+            });
+        }
+
+        if let Some(base) = name.strip_prefix("log_") {
+            return self.parse_logarithm(base);
+        }
+
+        match name.as_str() {
+            "fn" if is_call => {
+                self.advance();
+                let mut variables = vec![self.parse_variable()?];
+                while *self.peek() == Token::Symbol(",") {
+                    self.advance();
+                    variables.push(self.parse_variable()?);
+                }
+                self.expect(")")?;
+                self.expect("=")?;
+                Ok(Expression::Lambda {
+                    variables,
+                    expr: Box::new(self.parse_full()?),
+                })
+            }
+            "beta" if is_call => self.parse_pair(BinaryOperation::Beta),
+            // `solve(A, b)` solves a linear system, rather than an equation over a range.
+            "solve" if is_call && self.argument_count() == 2 => self.parse_pair(BinaryOperation::LinearSolve),
+            "dot" if is_call => self.parse_pair(BinaryOperation::Dot),
+            "cross" if is_call => self.parse_pair(BinaryOperation::Cross),
+            "angle" if is_call => self.parse_pair(BinaryOperation::Angle),
+            "proj" if is_call => self.parse_pair(BinaryOperation::Project),
+            "graph" if is_call => {
+                self.advance();
+                let expr = self.parse_full()?;
+                self.expect(")")?;
+                Ok(Expression::GraphExpression { expr: Box::new(expr) })
+            }
+            "derive" if is_call => {
+                self.advance();
+                let variable = self.parse_variable()?;
+                self.expect(",")?;
+                let expression = self.parse_full()?;
+                self.expect(")")?;
+                Ok(Expression::Derivative {
+                    variable: Box::new(variable),
+                    expression: Box::new(expression),
+                })
+            }
+            "if" if is_call => {
+                self.advance();
+                let condition = self.parse_full()?;
+                self.expect(",")?;
+                let consequent = self.parse_full()?;
+                self.expect(",")?;
+                let alternative = self.parse_full()?;
+                self.expect(")")?;
+                Ok(Expression::Conditional {
+                    condition: Box::new(condition),
+                    consequent: Box::new(consequent),
+                    alternative: Box::new(alternative),
+                })
+            }
+            "roots" if is_call => {
+                self.advance();
+                let polynomial = self.parse_full()?;
+                self.expect(",")?;
+                let variable = self.parse_variable()?;
+                self.expect(")")?;
+                Ok(Expression::Roots {
+                    polynomial: Box::new(polynomial),
+                    variable: Box::new(variable),
+                })
+            }
+            "sum" | "prod" | "solve" | "int" if is_call => {
+                self.advance();
+                let variable = self.parse_variable()?;
+                self.expect(",")?;
+                let minimum = self.parse_full()?;
+                self.expect(",")?;
+                let maximum = self.parse_full()?;
+                self.expect(",")?;
+                let expression = self.parse_full()?;
+                self.expect(")")?;
+                let (minimum, maximum, variable, expression) = (
+                    Box::new(minimum),
+                    Box::new(maximum),
+                    Box::new(variable),
+                    Box::new(expression),
+                );
+                match name.as_str() {
+                    "sum" => Ok(Expression::Summation { minimum, maximum, variable, expression }),
+                    "prod" => Ok(Expression::Product { minimum, maximum, variable, expression }),
+                    "int" => Ok(Expression::Integral {
+                        lower: minimum,
+                        upper: maximum,
+                        variable,
+                        integrand: expression,
+                    }),
+                    _ => Ok(Expression::Solve { minimum, maximum, variable, equation: expression }),
+                }
+            }
+            _ if is_call => {
+                self.advance();
+                self.parse_call(literal(name))
+            }
+            _ => Ok(literal(name)),
+        }
+    }
+
+    /// Parses the two arguments of a function like `beta(a, b)`, which is
+    /// stored as a binary operation.
+    fn parse_pair(&mut self, op: BinaryOperation) -> Result<Expression, String> {
+        self.advance();
+        let lhs = self.parse_full()?;
+        self.expect(",")?;
+        let rhs = self.parse_full()?;
+        self.expect(")")?;
+        Ok(Expression::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+            id: new_id(),
+        })
+    }
+
+    /// Counts the arguments of the call whose opening bracket is next, without consuming them.
+    fn argument_count(&self) -> usize {
+        let mut depth = 0;
+        let mut count = 1;
+        for (token, _) in &self.tokens[self.position..] {
+            match token {
+                Token::Symbol("(" | "[") => depth += 1,
+                Token::Symbol(")" | "]") if depth == 1 => return count,
+                Token::Symbol(")" | "]") => depth -= 1,
+                Token::Symbol(",") if depth == 1 => count += 1,
+                Token::End => break,
+                _ => {}
+            }
+        }
+        count
+    }
+
+    /// Parses `log_b(x)`, where the base is either written into the name or
+    /// given in parentheses as in `log_(b + 1)(x)`.
+    fn parse_logarithm(&mut self, base: &str) -> Result<Expression, String> {
+        let base = if base.is_empty() {
+            self.expect("(")?;
+            let base = self.parse_full()?;
+            self.expect(")")?;
+            base
+        } else {
+            literal(base.to_string())
+        };
+        self.expect("(")?;
+        let argument = self.parse_full()?;
+        self.expect(")")?;
+        Ok(Expression::Binary {
+            op: BinaryOperation::Log,
+            lhs: Box::new(base),
+            rhs: Box::new(argument),
+            id: new_id(),
+        })
+    }
+
+    /// Parses the arguments of `f(a, b, c)` into the curried call `f(a)(b)(c)`,
+    /// which may itself be followed by further argument lists.
+    fn parse_call(&mut self, function: Expression) -> Result<Expression, String> {
+        let arguments = self.parse_arguments(")")?;
+        if arguments.is_empty() {
+            return Err(format!("expected an argument for {}", function));
+        }
+        let call = arguments.into_iter().fold(function, |function, argument| Expression::Binary {
+            op: BinaryOperation::Invoke,
+            lhs: Box::new(function),
+            rhs: Box::new(argument),
+            id: new_id(),
+        });
+        if *self.peek() == Token::Symbol("(") {
+            self.advance();
+            return self.parse_call(call);
+        }
+        Ok(call)
+    }
+
+    fn parse_variable(&mut self) -> Result<Expression, String> {
+        match self.peek().clone() {
+            Token::Identifier(name) if UnaryOperation::from_name(&name).is_none() => {
+                self.advance();
+                Ok(literal(name))
+            }
+            _ => Err(format!("expected a variable name, {}", self.unexpected())),
+        }
+    }
+
+    fn parse_arguments(&mut self, close: &'static str) -> Result<Vec<Expression>, String> {
+        let mut exprs = vec![];
+        if *self.peek() == Token::Symbol(close) {
+            self.advance();
+            return Ok(exprs);
+        }
+        loop {
+            exprs.push(self.parse_full()?);
+            if *self.peek() == Token::Symbol(",") {
+                self.advance();
+                continue;
+            }
+            self.expect(close)?;
+            return Ok(exprs);
+        }
+    }
+}
+
+fn literal(content: String) -> Expression {
+    Expression::Literal {
+        content,
+        id: new_id(),
+        new_literal: false,
+    }
+}
+
+impl Expression {
+    /// Parses a typed formula such as `sin(x)^2 + 3*(y - 1)/2` into the same
+    /// tree the editor would build for it.
+    ///
+    /// Operators bind according to [`Precedence`] and associate to the left,
+    /// apart from `^` and `√`, which associate to the right, so the result is
+    /// left untouched by [`Expression::enforce_ooo`].
+    pub fn parse(source: &str) -> Result<Expression, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+        let expr = parser.parse_full()?;
+        if *parser.peek() != Token::End {
+            return Err(parser.unexpected());
+        }
+        Ok(expr)
+    }
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Expression::parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::context::Context;
+    use crate::math::expr::{Expression, UnaryOperation};
+    use crate::math::values::Value;
+
+    fn evaluate(source: &str) -> f64 {
+        let mut ctx = Context::default();
+        match Expression::parse(source).unwrap().eval(&mut ctx) {
+            Value::Number(number) => number.re,
+            other => panic!("`{}` gave {}", source, other),
+        }
+    }
+
+    #[test]
+    fn empty_input_is_an_error() {
+        assert_eq!(Expression::parse("").unwrap_err(), "unexpected end of input");
+        assert_eq!(Expression::parse("   ").unwrap_err(), "unexpected end of input");
+    }
+
+    #[test]
+    fn truncated_input_reports_the_end() {
+        assert_eq!(Expression::parse("(").unwrap_err(), "unexpected end of input");
+        assert_eq!(Expression::parse("1 +").unwrap_err(), "unexpected end of input");
+        assert_eq!(Expression::parse("sin(").unwrap_err(), "unexpected end of input");
+        assert_eq!(Expression::parse("[1, ").unwrap_err(), "unexpected end of input");
+        assert_eq!(Expression::parse(")").unwrap_err(), "unexpected `)` at position 0");
+    }
+
+    #[test]
+    fn negation_binds_looser_than_powers() {
+        let expr = Expression::parse("-x^2").unwrap();
+        let Expression::Unary { operation: UnaryOperation::Negate, expr, .. } = expr else {
+            panic!("`-x^2` is not a negation");
+        };
+        assert!(matches!(*expr, Expression::Binary { .. }));
+        assert_eq!(evaluate("-2^2"), -4.0);
+        assert_eq!(evaluate("(-2)^2"), 4.0);
+        assert_eq!(evaluate("2^-1"), 0.5);
+    }
+
+    #[test]
+    fn powers_associate_to_the_right() {
+        assert!((evaluate("2^3^2") - 512.0).abs() < 1e-9);
+        assert!((evaluate("(2^3)^2") - 64.0).abs() < 1e-9);
+        assert_eq!(evaluate("8 - 2 - 1"), 5.0);
+        assert_eq!(evaluate("8 / 2 / 2"), 2.0);
+    }
+
+    #[test]
+    fn numbers_have_one_decimal_point() {
+        assert_eq!(evaluate("1.25"), 1.25);
+        assert_eq!(evaluate(".5"), 0.5);
+        assert_eq!(evaluate("1.5e2"), 150.0);
+        assert_eq!(Expression::parse("1.2.3").unwrap_err(), "invalid number `1.2.3` at position 0");
+    }
+
+    #[test]
+    fn assignments_associate_to_the_right() {
+        let mut ctx = Context::default();
+        let expr = Expression::parse("a = b = 3").unwrap();
+        assert_eq!(expr.to_string(), "a = b = 3");
+        assert_eq!(expr.eval(&mut ctx).to_string(), "3+0i");
+        assert_eq!(ctx.resolve_variable(&"a".to_string()).unwrap().to_string(), "3+0i");
+        assert_eq!(ctx.resolve_variable(&"b".to_string()).unwrap().to_string(), "3+0i");
+        for source in ["a + 1 = 3", "2 = 3", "[a, b] = [1, 2]"] {
+            let result = Expression::parse(source).unwrap().eval(&mut ctx);
+            assert_eq!(result.to_string(), "error: \"can only assign to a variable\"", "assigning {}", source);
+        }
+    }
+
+    #[test]
+    fn factorials_bind_tighter_than_powers_and_negation() {
+        assert_eq!(evaluate("5!"), 120.0);
+        assert_eq!(evaluate("0!"), 1.0);
+        assert_eq!(evaluate("3!^2"), 36.0);
+        assert!((evaluate("2^3!") - 64.0).abs() < 1e-9);
+        assert_eq!(evaluate("-3!"), -6.0);
+        assert_eq!(evaluate("2 * 3! + 1"), 13.0);
+        assert_eq!(evaluate("3!!"), 720.0);
+        let expr = Expression::parse("-3!").unwrap();
+        let Expression::Unary { operation, expr, .. } = expr else {
+            panic!("expected a negation, got {}", expr);
+        };
+        assert_eq!(operation, UnaryOperation::Negate);
+        assert!(matches!(*expr, Expression::Unary { operation: UnaryOperation::Factorial, .. }));
+        let result = Expression::parse("(-3)!").unwrap().eval(&mut Context::default());
+        assert_eq!(result.to_string(), "error: \"factorial is undefined for negative integers\"");
+    }
+}
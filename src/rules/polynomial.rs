@@ -0,0 +1,438 @@
+use crate::math::context::Context;
+use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
+use crate::rules::simplify::{is_scalar, power, product, rational, rational_expr, Factor, Sum, Term};
+use num::bigint::BigInt;
+use num::rational::BigRational;
+use num::{Integer, One, Signed, ToPrimitive, Zero};
+
+/// The largest power of a sum that is multiplied out.
+const MAX_EXPANDED_POWER: i64 = 32;
+/// Coefficients above this are not searched for rational roots, as
+/// listing their divisors would take too long.
+const MAX_ROOT_SEARCH: u64 = 1_000_000_000_000;
+
+impl Expression {
+    /// Multiplies out products of sums and whole powers of sums, then collects like terms.
+    /// Sums that may hold a vector or matrix, going by `ctx`, are left as they are.
+    pub fn expand(&self, ctx: &Context) -> Expression {
+        self.distribute(ctx).simplify(ctx)
+    }
+
+    /// Writes the expression as a product of simpler factors.
+    ///
+    /// Factors common to every term are pulled out first. What remains is, when it
+    /// is a polynomial in a single variable, split further at its rational roots,
+    /// leaving only factors that are irreducible over the rationals or of degree one.
+    pub fn factor(&self, ctx: &Context) -> Expression {
+        let mut sum = Sum::default();
+        sum.add(&self.expand(ctx), &BigRational::one(), ctx);
+        sum.terms.retain(|term| !term.coefficient.is_zero());
+        if sum.terms.len() < 2 {
+            return sum.build();
+        }
+
+        let common = common_factor(&mut sum);
+        let mut factors: Vec<(Expression, u32)> = common
+            .factors
+            .iter()
+            .map(|factor| (power(factor.base.clone(), factor.exponent.clone()), 1))
+            .collect();
+        let mut coefficient = common.coefficient;
+        match univariate(&sum) {
+            Some((variable, mut coefficients)) => {
+                if coefficients.last().is_some_and(BigRational::is_negative) {
+                    coefficient = -coefficient;
+                    coefficients.iter_mut().for_each(|value| *value = -&*value);
+                }
+                factors.extend(factor_polynomial(&variable, coefficients));
+            }
+            None => factors.push((sum.build(), 1)),
+        }
+
+        let mut items: Vec<Expression> = factors
+            .into_iter()
+            .map(|(factor, multiplicity)| power(factor, rational_expr(&BigRational::from_integer(multiplicity.into()))))
+            .collect();
+        if coefficient.abs() != BigRational::one() {
+            items.insert(0, rational_expr(&coefficient.abs()));
+        }
+        if coefficient.is_negative() {
+            items[0] = Expression::unary(UnaryOperation::Negate, items[0].clone());
+        }
+        let mut result = product(items);
+        result.renew_ids();
+        result
+    }
+
+    /// Reads the expression as a polynomial in `variable`, returning its coefficients
+    /// from the constant term up. Coefficients may still mention other variables.
+    pub fn coefficients(&self, variable: &str, ctx: &Context) -> Result<Vec<Expression>, String> {
+        let not_polynomial = || format!("{} is not a polynomial in {}", self, variable);
+        let mut sum = Sum::default();
+        sum.add(&self.expand(ctx), &BigRational::one(), ctx);
+
+        let mut coefficients: Vec<Sum> = vec![];
+        for term in sum.terms.into_iter().filter(|term| !term.coefficient.is_zero()) {
+            let mut degree = 0;
+            let mut factors = vec![];
+            for factor in term.factors {
+                match &factor.base {
+                    Expression::Literal { content, .. } if content == variable => {
+                        degree = rational(&factor.exponent)
+                            .filter(|exponent| exponent.is_integer() && exponent.is_positive())
+                            .and_then(|exponent| exponent.to_integer().to_usize())
+                            .ok_or_else(not_polynomial)?;
+                    }
+                    base if base.depends_on(variable) || factor.exponent.depends_on(variable) => {
+                        return Err(not_polynomial());
+                    }
+                    _ => factors.push(factor),
+                }
+            }
+            if coefficients.len() <= degree {
+                coefficients.resize_with(degree + 1, Sum::default);
+            }
+            coefficients[degree].terms.push(Term {
+                coefficient: term.coefficient,
+                factors,
+            });
+        }
+        Ok(coefficients.into_iter().map(Sum::build).collect())
+    }
+
+    fn distribute(&self, ctx: &Context) -> Expression {
+        match self {
+            Expression::Parenthesis { expr, .. } => expr.distribute(ctx),
+            // Numbers spread over a sum of vectors or matrices differently than
+            // they multiply them, so only scalars are multiplied out.
+            Expression::Binary {
+                op: BinaryOperation::Multiply | BinaryOperation::Power,
+                ..
+            } if !is_scalar(self, ctx) => self.clone(),
+            Expression::Binary {
+                op: BinaryOperation::Divide,
+                rhs,
+                ..
+            } if !is_scalar(rhs, ctx) => self.clone(),
+            Expression::Binary {
+                op: BinaryOperation::Multiply,
+                lhs,
+                rhs,
+                ..
+            } => multiply_out(&lhs.distribute(ctx), &rhs.distribute(ctx), ctx),
+            Expression::Binary {
+                op: BinaryOperation::Divide,
+                lhs,
+                rhs,
+                ..
+            } => {
+                let denominator = rhs.distribute(ctx);
+                join(
+                    summands(&lhs.distribute(ctx))
+                        .into_iter()
+                        .map(|(negative, term)| {
+                            (negative, Expression::binary(BinaryOperation::Divide, term, denominator.clone()))
+                        })
+                        .collect(),
+                )
+            }
+            Expression::Binary {
+                op: BinaryOperation::Power,
+                lhs: base,
+                rhs: exponent,
+                ..
+            } => {
+                let base = base.distribute(ctx);
+                let exponent = exponent.simplify(ctx);
+                let times = rational(&exponent)
+                    .filter(BigRational::is_integer)
+                    .and_then(|exponent| exponent.to_integer().to_i64());
+                match times {
+                    Some(times) if (0..=MAX_EXPANDED_POWER).contains(&times) => {
+                        (0..times).fold(Expression::number(1.0), |result, _| multiply_out(&result, &base, ctx))
+                    }
+                    _ => Expression::binary(BinaryOperation::Power, base, exponent),
+                }
+            }
+            Expression::Binary { op, lhs, rhs, .. } => Expression::binary(*op, lhs.distribute(ctx), rhs.distribute(ctx)),
+            Expression::Unary { operation, expr, .. } => Expression::unary(operation.clone(), expr.distribute(ctx)),
+            _ => self.clone(),
+        }
+    }
+}
+
+/// Multiplies every term of `lhs` with every term of `rhs`, collecting like terms
+/// right away so that repeated multiplication does not blow up.
+fn multiply_out(lhs: &Expression, rhs: &Expression, ctx: &Context) -> Expression {
+    let mut terms = vec![];
+    for (lhs_negative, lhs) in summands(lhs) {
+        for (rhs_negative, rhs) in summands(rhs) {
+            terms.push((
+                lhs_negative != rhs_negative,
+                Expression::binary(BinaryOperation::Multiply, lhs.clone(), rhs.clone()),
+            ));
+        }
+    }
+    join(terms).simplify(ctx)
+}
+
+/// Splits a sum into its terms along with whether each one is subtracted.
+fn summands(expr: &Expression) -> Vec<(bool, Expression)> {
+    match expr {
+        Expression::Parenthesis { expr, .. } => summands(expr),
+        Expression::Binary {
+            op: op @ (BinaryOperation::Add | BinaryOperation::Sub),
+            lhs,
+            rhs,
+            ..
+        } => {
+            let mut terms = summands(lhs);
+            let subtract = *op == BinaryOperation::Sub;
+            terms.extend(
+                summands(rhs)
+                    .into_iter()
+                    .map(|(negative, term)| (negative != subtract, term)),
+            );
+            terms
+        }
+        Expression::Unary {
+            operation: UnaryOperation::Negate,
+            expr,
+            ..
+        } => summands(expr)
+            .into_iter()
+            .map(|(negative, term)| (!negative, term))
+            .collect(),
+        _ => vec![(false, expr.clone())],
+    }
+}
+
+fn join(terms: Vec<(bool, Expression)>) -> Expression {
+    terms
+        .into_iter()
+        .fold(None, |sum, (negative, term)| {
+            Some(match sum {
+                None if negative => Expression::unary(UnaryOperation::Negate, term),
+                None => term,
+                Some(sum) if negative => Expression::binary(BinaryOperation::Sub, sum, term),
+                Some(sum) => Expression::binary(BinaryOperation::Add, sum, term),
+            })
+        })
+        .unwrap_or_else(|| Expression::number(0.0))
+}
+
+/// Divides the terms by the largest factor they share, returning that factor.
+/// The sign is chosen so that the first term stays positive.
+fn common_factor(sum: &mut Sum) -> Term {
+    let numerators = sum.terms.iter().fold(BigInt::zero(), |gcd, term| gcd.gcd(term.coefficient.numer()));
+    let denominators = sum.terms.iter().fold(BigInt::one(), |lcm, term| lcm.lcm(term.coefficient.denom()));
+    let mut coefficient = BigRational::new(numerators, denominators);
+    if sum.terms[0].coefficient.is_negative() {
+        coefficient = -coefficient;
+    }
+
+    // Only bases raised to a constant in every term can be pulled out.
+    let mut factors: Vec<Factor> = vec![];
+    for factor in &sum.terms[0].factors {
+        let key = factor.base.to_string();
+        let exponents: Option<Vec<BigRational>> = sum
+            .terms
+            .iter()
+            .map(|term| {
+                let factor = term.factors.iter().find(|other| other.base.to_string() == key)?;
+                rational(&factor.exponent).filter(BigRational::is_positive)
+            })
+            .collect();
+        if let Some(exponent) = exponents.and_then(|exponents| exponents.into_iter().min()) {
+            factors.push(Factor {
+                base: factor.base.clone(),
+                exponent: rational_expr(&exponent),
+            });
+        }
+    }
+
+    for term in &mut sum.terms {
+        term.coefficient = &term.coefficient / &coefficient;
+        for common in &factors {
+            let key = common.base.to_string();
+            let factor = term.factors.iter_mut().find(|factor| factor.base.to_string() == key).unwrap();
+            let exponent = rational(&factor.exponent).unwrap() - rational(&common.exponent).unwrap();
+            factor.exponent = rational_expr(&exponent);
+        }
+        term.factors
+            .retain(|factor| !rational(&factor.exponent).is_some_and(|exponent| exponent.is_zero()));
+    }
+
+    Term { coefficient, factors }
+}
+
+/// Reads the terms as a polynomial in a single variable, returning the
+/// variable and the coefficients from the constant term up.
+fn univariate(sum: &Sum) -> Option<(Expression, Vec<BigRational>)> {
+    let mut variable: Option<&Expression> = None;
+    let mut coefficients: Vec<BigRational> = vec![];
+    for term in &sum.terms {
+        let degree = match term.factors.as_slice() {
+            [] => 0,
+            [factor] => {
+                if !matches!(factor.base, Expression::Literal { .. }) {
+                    return None;
+                }
+                match variable {
+                    Some(variable) if variable.to_string() != factor.base.to_string() => return None,
+                    _ => variable = Some(&factor.base),
+                }
+                let exponent = rational(&factor.exponent).filter(|exponent| exponent.is_integer() && exponent.is_positive())?;
+                exponent.to_integer().to_usize()?
+            }
+            _ => return None,
+        };
+        if coefficients.len() <= degree {
+            coefficients.resize(degree + 1, BigRational::zero());
+        }
+        coefficients[degree] += &term.coefficient;
+    }
+    Some((variable?.clone(), coefficients))
+}
+
+/// Splits a polynomial with integer coefficients at its rational roots, using
+/// the rational root theorem to find candidates.
+fn factor_polynomial(variable: &Expression, mut coefficients: Vec<BigRational>) -> Vec<(Expression, u32)> {
+    let mut factors: Vec<(Expression, u32)> = vec![];
+    while coefficients.len() > 2 {
+        let Some(root) = rational_root(&coefficients) else {
+            break;
+        };
+        coefficients = deflate(&coefficients, &root);
+        // Writing the root `p/q` as the factor `q x - p` leaves integer coefficients
+        // behind once the quotient is divided by `q`.
+        let linear = polynomial(
+            variable,
+            &[-BigRational::from_integer(root.numer().clone()), BigRational::from_integer(root.denom().clone())],
+        );
+        coefficients.iter_mut().for_each(|value| *value = &*value / root.denom());
+        push_factor(&mut factors, linear);
+    }
+    push_factor(&mut factors, polynomial(variable, &coefficients));
+    factors
+}
+
+/// Adds a factor to the list, counting repeated ones as powers.
+fn push_factor(factors: &mut Vec<(Expression, u32)>, factor: Expression) {
+    let key = factor.to_string();
+    match factors.iter_mut().find(|(existing, _)| existing.to_string() == key) {
+        Some((_, multiplicity)) => *multiplicity += 1,
+        None => factors.push((factor, 1)),
+    }
+}
+
+fn rational_root(coefficients: &[BigRational]) -> Option<BigRational> {
+    let constant = coefficients.first()?.to_integer().abs().to_u64()?;
+    let leading = coefficients.last()?.to_integer().abs().to_u64()?;
+    if constant > MAX_ROOT_SEARCH || leading > MAX_ROOT_SEARCH {
+        return None;
+    }
+    for numerator in divisors(constant) {
+        for denominator in divisors(leading) {
+            for sign in [1, -1] {
+                let candidate = BigRational::new(BigInt::from(numerator) * sign, BigInt::from(denominator));
+                if evaluate(coefficients, &candidate).is_zero() {
+                    return Some(candidate);
+                }
+            }
+        }
+    }
+    None
+}
+
+fn divisors(value: u64) -> Vec<u64> {
+    let mut divisors = vec![];
+    let mut candidate = 1;
+    while candidate * candidate <= value {
+        if value.is_multiple_of(candidate) {
+            divisors.push(candidate);
+            if candidate * candidate != value {
+                divisors.push(value / candidate);
+            }
+        }
+        candidate += 1;
+    }
+    divisors
+}
+
+fn evaluate(coefficients: &[BigRational], x: &BigRational) -> BigRational {
+    coefficients
+        .iter()
+        .rev()
+        .fold(BigRational::zero(), |result, coefficient| result * x + coefficient)
+}
+
+/// Divides the polynomial by `x - root` with synthetic division.
+fn deflate(coefficients: &[BigRational], root: &BigRational) -> Vec<BigRational> {
+    let mut quotient = vec![BigRational::zero(); coefficients.len() - 1];
+    let mut carry = BigRational::zero();
+    for degree in (1..coefficients.len()).rev() {
+        carry = &coefficients[degree] + carry * root;
+        quotient[degree - 1] = carry.clone();
+    }
+    quotient
+}
+
+/// Writes out the polynomial with the given coefficients, highest degree first.
+fn polynomial(variable: &Expression, coefficients: &[BigRational]) -> Expression {
+    let terms = coefficients
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, coefficient)| !coefficient.is_zero())
+        .map(|(degree, coefficient)| Term {
+            coefficient: coefficient.clone(),
+            factors: match degree {
+                0 => vec![],
+                _ => vec![Factor {
+                    base: variable.clone(),
+                    exponent: rational_expr(&BigRational::from_integer(degree.into())),
+                }],
+            },
+        })
+        .collect();
+    Sum { terms }.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::context::Context;
+    use crate::math::expr::Expression;
+
+    fn parse(source: &str) -> Expression {
+        Expression::parse(source).unwrap()
+    }
+
+    #[test]
+    fn expands_products_and_powers_of_sums() {
+        assert_eq!(parse("(x + 1)^2").expand(&Context::default()).to_string(), "x^2 + 2 * x + 1");
+        assert_eq!(parse("(x - y) * (x + y)").expand(&Context::default()).to_string(), "x^2 - y^2");
+    }
+
+    #[test]
+    fn factors_at_rational_roots() {
+        assert_eq!(parse("6 * x^2 - x - 1").factor(&Context::default()).to_string(), "(2 * x - 1) * (3 * x + 1)");
+        assert_eq!(parse("x^3 - 6 * x^2 + 11 * x - 6").factor(&Context::default()).to_string(), "(x - 1) * (x - 2) * (x - 3)");
+        assert_eq!(parse("x^2 - 2 * x + 1").factor(&Context::default()).to_string(), "(x - 1)^2");
+        assert_eq!(parse("3 - 5 * x + 2 * x").factor(&Context::default()).to_string(), "-3 * (x - 1)");
+    }
+
+    #[test]
+    fn factors_out_common_factors() {
+        assert_eq!(parse("2 * x * y + 4 * x").factor(&Context::default()).to_string(), "2 * x * (y + 2)");
+        assert_eq!(parse("x^2 + 1").factor(&Context::default()).to_string(), "x^2 + 1");
+    }
+
+    #[test]
+    fn reads_coefficients() {
+        let coefficients = parse("(x + y)^2").coefficients("x", &Context::default()).unwrap();
+        let coefficients: Vec<String> = coefficients.iter().map(Expression::to_string).collect();
+        assert_eq!(coefficients, ["y^2", "2 * y", "1"]);
+        assert_eq!(parse("sin(x)").coefficients("x", &Context::default()).unwrap_err(), "sin(x) is not a polynomial in x");
+    }
+}
@@ -0,0 +1,185 @@
+use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
+use crate::rules::ooo::Precedence;
+use std::fmt::{Display, Formatter};
+
+/// Formats expressions as linear infix text that [`Expression::parse`] reads back,
+/// emitting only the parentheses the tree itself requires.
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Unary { operation: UnaryOperation::Negate, expr, .. } => {
+                write!(f, "-")?;
+                write_operand(f, expr, Precedence::Exponent, true)
+            }
+            Expression::Unary { operation: UnaryOperation::Factorial, expr, .. } => {
+                match **expr {
+                    Expression::Unary { operation: UnaryOperation::Negate, .. } => write!(f, "({})", expr)?,
+                    _ => write_operand(f, expr, Precedence::Unary, false)?,
+                }
+                write!(f, "!")
+            }
+            Expression::Unary { operation, expr, .. } => write!(f, "{}({})", operation, expr),
+            Expression::Binary { op: BinaryOperation::Invoke, lhs, rhs, .. } => {
+                // The curried call `f(a)(b)` is written as `f(a, b)`.
+                let mut function = &**lhs;
+                let mut arguments = vec![&**rhs];
+                while let Expression::Binary { op: BinaryOperation::Invoke, lhs, rhs, .. } = function {
+                    arguments.push(rhs);
+                    function = lhs;
+                }
+                match function {
+                    Expression::Literal { .. } | Expression::Parenthesis { .. } => write!(f, "{}", function)?,
+                    _ => write!(f, "({})", function)?,
+                }
+                write!(f, "(")?;
+                for (index, argument) in arguments.iter().rev().enumerate() {
+                    if index != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ")")
+            }
+            Expression::Binary { op: BinaryOperation::Log, lhs, rhs, .. } => {
+                match **lhs {
+                    Expression::Literal { ref content, .. }
+                        if content.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+                    {
+                        write!(f, "log_{}", content)?
+                    }
+                    _ => write!(f, "log_({})", lhs)?,
+                }
+                write!(f, "({})", rhs)
+            }
+            Expression::Binary { op, lhs, rhs, .. } if op.is_function() => {
+                write!(f, "{}({}, {})", op, lhs, rhs)
+            }
+            Expression::Binary { op, lhs, rhs, .. } => {
+                write_operand(f, lhs, op.precedence(), false)?;
+                match op {
+                    BinaryOperation::Power | BinaryOperation::Root => write!(f, "{}", op)?,
+                    _ => write!(f, " {} ", op)?,
+                }
+                write_operand(f, rhs, op.precedence(), true)
+            }
+            Expression::Vector { exprs, .. } => {
+                write!(f, "[")?;
+                for (index, expr) in exprs.iter().enumerate() {
+                    if index != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", expr)?;
+                }
+                write!(f, "]")
+            }
+            Expression::Literal { content, .. } => write!(f, "{}", content),
+            Expression::Parenthesis { expr, .. } => write!(f, "({})", expr),
+            Expression::GraphExpression { expr } => write!(f, "graph({})", expr),
+            Expression::Summation {
+                minimum,
+                maximum,
+                variable,
+                expression,
+            } => write!(f, "sum({}, {}, {}, {})", variable, minimum, maximum, expression),
+            Expression::Product {
+                minimum,
+                maximum,
+                variable,
+                expression,
+            } => write!(f, "prod({}, {}, {}, {})", variable, minimum, maximum, expression),
+            Expression::Solve {
+                minimum,
+                maximum,
+                variable,
+                equation,
+            } => write!(f, "solve({}, {}, {}, {})", variable, minimum, maximum, equation),
+            Expression::Integral {
+                lower,
+                upper,
+                variable,
+                integrand,
+            } => write!(f, "int({}, {}, {}, {})", variable, lower, upper, integrand),
+            Expression::Derivative {
+                variable,
+                expression,
+            } => write!(f, "derive({}, {})", variable, expression),
+            Expression::Roots {
+                polynomial,
+                variable,
+            } => write!(f, "roots({}, {})", polynomial, variable),
+            Expression::Conditional {
+                condition,
+                consequent,
+                alternative,
+            } => write!(f, "if({}, {}, {})", condition, consequent, alternative),
+            Expression::Lambda { variables, expr } => {
+                write!(f, "fn(")?;
+                for (index, variable) in variables.iter().enumerate() {
+                    if index != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", variable)?;
+                }
+                write!(f, ") = {}", expr)
+            }
+        }
+    }
+}
+
+/// Writes `expr` as the operand of an operator with the given precedence.
+/// Operands on the right need parentheses on ties as every operator is left-associative.
+fn write_operand(
+    f: &mut Formatter<'_>,
+    expr: &Expression,
+    precedence: Precedence,
+    right: bool,
+) -> std::fmt::Result {
+    if expr.needs_brackets(precedence, right) {
+        write!(f, "({})", expr)
+    } else {
+        write!(f, "{}", expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::expr::Expression;
+
+    /// Printing a parsed formula gives it back, and parsing the result again
+    /// prints the same text.
+    fn round_trip(source: &str) {
+        let printed = Expression::parse(source).unwrap().to_string();
+        assert_eq!(printed, source);
+        assert_eq!(Expression::parse(&printed).unwrap().to_string(), printed);
+    }
+
+    #[test]
+    fn keeps_only_needed_brackets() {
+        round_trip("1 + 2 * 3");
+        round_trip("(1 + 2) * 3");
+        round_trip("1 - (2 - 3)");
+        round_trip("a ÷ (b * c)");
+        round_trip("sin(x)^2 + 3 * (y - 1) ÷ 2");
+    }
+
+    #[test]
+    fn powers_and_negation() {
+        round_trip("-x^2");
+        round_trip("(-x)^2");
+        round_trip("2^3^2");
+        round_trip("(2^3)^2");
+        round_trip("2^-x");
+        round_trip("-(a + b)");
+        round_trip("(-3)!");
+    }
+
+    #[test]
+    fn calls_and_functions() {
+        round_trip("1 + f(2)");
+        round_trip("f(a, b)");
+        round_trip("beta(2, 3)");
+        round_trip("solve([[2, 1], [1, 3]], [1, 2])");
+        round_trip("log_2(8)");
+        round_trip("a = 1 + 2");
+    }
+}
@@ -0,0 +1,650 @@
+use crate::math::context::{Context, GlobalContext};
+use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
+use crate::math::values::Value;
+use num::bigint::BigInt;
+use num::rational::BigRational;
+use num::{One, Signed, ToPrimitive, Zero};
+
+/// How many times the rules are reapplied before settling for the result.
+const MAX_PASSES: usize = 8;
+/// The largest integer power that is multiplied out when folding constants.
+const MAX_FOLDED_POWER: i64 = 64;
+
+impl Expression {
+    /// Rewrites the expression into a simpler form with the same value.
+    ///
+    /// Constants are folded, additions of zero and multiplications by one are
+    /// dropped, like terms are collected and powers of the same base are merged.
+    /// Only products of scalars are reordered or cancelled, so products that may
+    /// hold a vector or matrix, going by the variables bound in `ctx`, keep their
+    /// order. Derivatives are replaced with the derived expression when possible.
+    pub fn simplify(&self, ctx: &Context) -> Expression {
+        let mut current = self.simplify_once(ctx);
+        for _ in 1..MAX_PASSES {
+            let next = current.simplify_once(ctx);
+            if next.to_string() == current.to_string() {
+                break;
+            }
+            current = next;
+        }
+        current.renew_ids();
+        current
+    }
+
+    fn simplify_once(&self, ctx: &Context) -> Expression {
+        match self {
+            Expression::Parenthesis { expr, .. } => expr.simplify_once(ctx),
+            Expression::Literal { .. } => self.clone(),
+            Expression::Unary {
+                operation: UnaryOperation::Negate,
+                ..
+            }
+            | Expression::Binary {
+                op: BinaryOperation::Add
+                | BinaryOperation::Sub
+                | BinaryOperation::Multiply
+                | BinaryOperation::Divide
+                | BinaryOperation::Power,
+                ..
+            } => {
+                let mut sum = Sum::default();
+                sum.add(self, &BigRational::one(), ctx);
+                sum.build()
+            }
+            Expression::Unary { operation, expr, .. } => {
+                fold(Expression::unary(operation.clone(), expr.simplify_once(ctx)))
+            }
+            Expression::Binary { op, lhs, rhs, .. } => {
+                fold(Expression::binary(*op, lhs.simplify_once(ctx), rhs.simplify_once(ctx)))
+            }
+            Expression::Vector { exprs, .. } => Expression::Vector {
+                exprs: exprs.iter().map(|expr| expr.simplify_once(ctx)).collect(),
+                id: 0,
+            },
+            Expression::GraphExpression { expr } => Expression::GraphExpression {
+                expr: Box::new(expr.simplify_once(ctx)),
+            },
+            Expression::Summation {
+                minimum,
+                maximum,
+                variable,
+                expression,
+            } => Expression::Summation {
+                minimum: Box::new(minimum.simplify_once(ctx)),
+                maximum: Box::new(maximum.simplify_once(ctx)),
+                variable: variable.clone(),
+                expression: Box::new(expression.simplify_once(ctx)),
+            },
+            Expression::Product {
+                minimum,
+                maximum,
+                variable,
+                expression,
+            } => Expression::Product {
+                minimum: Box::new(minimum.simplify_once(ctx)),
+                maximum: Box::new(maximum.simplify_once(ctx)),
+                variable: variable.clone(),
+                expression: Box::new(expression.simplify_once(ctx)),
+            },
+            Expression::Solve {
+                minimum,
+                maximum,
+                variable,
+                equation,
+            } => Expression::Solve {
+                minimum: Box::new(minimum.simplify_once(ctx)),
+                maximum: Box::new(maximum.simplify_once(ctx)),
+                variable: variable.clone(),
+                equation: Box::new(equation.simplify_once(ctx)),
+            },
+            Expression::Integral {
+                lower,
+                upper,
+                variable,
+                integrand,
+            } => Expression::Integral {
+                lower: Box::new(lower.simplify_once(ctx)),
+                upper: Box::new(upper.simplify_once(ctx)),
+                variable: variable.clone(),
+                integrand: Box::new(integrand.simplify_once(ctx)),
+            },
+            Expression::Derivative { variable, expression } => {
+                if let Expression::Literal { content, .. } = &**variable {
+                    if let Ok(derivative) = expression.derive(content) {
+                        return derivative.simplify_once(ctx);
+                    }
+                }
+                Expression::Derivative {
+                    variable: variable.clone(),
+                    expression: Box::new(expression.simplify_once(ctx)),
+                }
+            }
+            Expression::Roots { polynomial, variable } => Expression::Roots {
+                polynomial: Box::new(polynomial.simplify_once(ctx)),
+                variable: variable.clone(),
+            },
+            Expression::Conditional {
+                condition,
+                consequent,
+                alternative,
+            } => Expression::Conditional {
+                condition: Box::new(condition.simplify_once(ctx)),
+                consequent: Box::new(consequent.simplify_once(ctx)),
+                alternative: Box::new(alternative.simplify_once(ctx)),
+            },
+            Expression::Lambda { variables, expr } => Expression::Lambda {
+                variables: variables.clone(),
+                expr: Box::new(expr.simplify_once(ctx)),
+            },
+        }
+    }
+}
+
+/// Evaluates an operation on constants, keeping the result only when it is a
+/// whole number so that values like `sqrt(2)` stay exact.
+fn fold(expr: Expression) -> Expression {
+    let operands: Vec<&Expression> = match &expr {
+        Expression::Unary { expr, .. } => vec![expr],
+        Expression::Binary {
+            op: BinaryOperation::Store,
+            ..
+        } => return expr,
+        Expression::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+        _ => return expr,
+    };
+    if !operands.iter().all(|operand| rational(operand).is_some()) {
+        return expr;
+    }
+    match expr.eval(&mut Context::default()) {
+        Value::Number(value)
+            if value.im == 0.0 && value.re.fract() == 0.0 && value.re.abs() < 2f64.powi(53) =>
+        {
+            Expression::number(value.re)
+        }
+        _ => expr,
+    }
+}
+
+/// A sum of terms, built up while flattening nested additions and subtractions.
+#[derive(Default)]
+pub(super) struct Sum {
+    pub(super) terms: Vec<Term>,
+}
+
+/// A rational coefficient multiplied by powers of other expressions.
+pub(super) struct Term {
+    pub(super) coefficient: BigRational,
+    pub(super) factors: Vec<Factor>,
+}
+
+pub(super) struct Factor {
+    pub(super) base: Expression,
+    pub(super) exponent: Expression,
+}
+
+impl Sum {
+    pub(super) fn add(&mut self, expr: &Expression, scale: &BigRational, ctx: &Context) {
+        match expr {
+            Expression::Parenthesis { expr, .. } => self.add(expr, scale, ctx),
+            Expression::Binary {
+                op: BinaryOperation::Add,
+                lhs,
+                rhs,
+                ..
+            } => {
+                self.add(lhs, scale, ctx);
+                self.add(rhs, scale, ctx);
+            }
+            Expression::Binary {
+                op: BinaryOperation::Sub,
+                lhs,
+                rhs,
+                ..
+            } => {
+                self.add(lhs, scale, ctx);
+                self.add(rhs, &-scale, ctx);
+            }
+            Expression::Unary {
+                operation: UnaryOperation::Negate,
+                expr,
+                ..
+            } => self.add(expr, &-scale, ctx),
+            _ => {
+                let mut term = Term {
+                    coefficient: scale.clone(),
+                    factors: vec![],
+                };
+                term.multiply(expr, &BigRational::one(), ctx);
+                self.push(term);
+            }
+        }
+    }
+
+    fn push(&mut self, term: Term) {
+        if term.coefficient.is_zero() {
+            return;
+        }
+        let key = term.key();
+        match self.terms.iter_mut().find(|existing| existing.key() == key) {
+            Some(existing) => existing.coefficient += term.coefficient,
+            None => self.terms.push(term),
+        }
+    }
+
+    /// Writes the terms back out, leaving the constant term for last.
+    pub(super) fn build(self) -> Expression {
+        let (constants, mut terms): (Vec<Term>, Vec<Term>) = self
+            .terms
+            .into_iter()
+            .filter(|term| !term.coefficient.is_zero())
+            .partition(|term| term.factors.is_empty());
+        terms.extend(constants);
+
+        let mut result: Option<Expression> = None;
+        for term in terms {
+            result = Some(match result {
+                None => term.build(&term.coefficient),
+                Some(sum) if term.coefficient.is_negative() => {
+                    Expression::binary(BinaryOperation::Sub, sum, term.build(&-&term.coefficient))
+                }
+                Some(sum) => Expression::binary(BinaryOperation::Add, sum, term.build(&term.coefficient)),
+            });
+        }
+        result.unwrap_or_else(|| Expression::number(0.0))
+    }
+}
+
+impl Term {
+    /// Multiplies the term by `expr` raised to the whole number `power`.
+    fn multiply(&mut self, expr: &Expression, power: &BigRational, ctx: &Context) {
+        match expr {
+            Expression::Parenthesis { expr, .. } => self.multiply(expr, power, ctx),
+            // Products of vectors and matrices do not commute, and their powers are
+            // not repeated products, so anything but scalars is kept whole and in order.
+            Expression::Binary {
+                op: op @ (BinaryOperation::Multiply | BinaryOperation::Divide | BinaryOperation::Power),
+                lhs,
+                rhs,
+                ..
+            } if !is_scalar(expr, ctx) => {
+                let product = Expression::binary(*op, lhs.simplify_once(ctx), rhs.simplify_once(ctx));
+                self.push_factor(product, rational_expr(power), ctx);
+            }
+            Expression::Binary {
+                op: BinaryOperation::Multiply,
+                lhs,
+                rhs,
+                ..
+            } => {
+                self.multiply(lhs, power, ctx);
+                self.multiply(rhs, power, ctx);
+            }
+            Expression::Binary {
+                op: BinaryOperation::Divide,
+                lhs,
+                rhs,
+                ..
+            } => {
+                self.multiply(lhs, power, ctx);
+                self.multiply(rhs, &-power, ctx);
+            }
+            Expression::Unary {
+                operation: UnaryOperation::Negate,
+                expr,
+                ..
+            } => {
+                if power.to_integer() % 2 != BigInt::zero() {
+                    self.coefficient = -&self.coefficient;
+                }
+                self.multiply(expr, power, ctx);
+            }
+            Expression::Binary {
+                op: BinaryOperation::Power,
+                lhs: base,
+                rhs: exponent,
+                ..
+            } => {
+                let exponent = exponent.simplify_once(ctx);
+                match rational(&exponent) {
+                    Some(exponent) if exponent.is_integer() => self.multiply(base, &(exponent * power), ctx),
+                    _ => self.push_factor(base.simplify_once(ctx), scale_exponent(&exponent, power, ctx), ctx),
+                }
+            }
+            _ => {
+                if let Some(value) = rational(expr) {
+                    match foldable_power(&value, power) {
+                        Some(exponent) => self.coefficient *= pow(&value, exponent),
+                        None => self.push_factor(expr.clone(), rational_expr(power), ctx),
+                    }
+                    return;
+                }
+                let simplified = expr.simplify_once(ctx);
+                // A sum may simplify into a single product, like `x + x` into `2 * x`.
+                match simplified {
+                    Expression::Binary {
+                        op: BinaryOperation::Multiply | BinaryOperation::Divide | BinaryOperation::Power,
+                        ..
+                    }
+                    | Expression::Unary {
+                        operation: UnaryOperation::Negate,
+                        ..
+                    } => self.multiply(&simplified, power, ctx),
+                    _ if rational(&simplified).is_some() => self.multiply(&simplified, power, ctx),
+                    _ => self.push_factor(simplified, rational_expr(power), ctx),
+                }
+            }
+        }
+    }
+
+    fn push_factor(&mut self, base: Expression, exponent: Expression, ctx: &Context) {
+        let key = base.to_string();
+        let index = match self.factors.iter().position(|factor| factor.base.to_string() == key) {
+            Some(index) => {
+                let factor = &mut self.factors[index];
+                factor.exponent = add_exponents(&factor.exponent, &exponent, ctx);
+                index
+            }
+            None => {
+                self.factors.push(Factor { base, exponent });
+                self.factors.len() - 1
+            }
+        };
+
+        // Merging can leave a constant to a whole power, like `2^(1/2) * 2^(1/2)`.
+        let factor = &self.factors[index];
+        if let Some(exponent) = rational(&factor.exponent) {
+            if exponent.is_zero() {
+                self.factors.remove(index);
+            } else if let Some(exponent) = rational(&factor.base).and_then(|base| foldable_power(&base, &exponent)) {
+                let factor = self.factors.remove(index);
+                self.coefficient *= pow(&rational(&factor.base).unwrap(), exponent);
+            }
+        }
+    }
+
+    /// Identifies like terms, which only differ in their coefficient.
+    fn key(&self) -> String {
+        let mut factors: Vec<String> = self
+            .factors
+            .iter()
+            .map(|factor| format!("{}^{}", factor.base, factor.exponent))
+            .collect();
+        factors.sort();
+        factors.join(" * ")
+    }
+
+    /// Writes the term out with the given coefficient, moving factors with
+    /// negative exponents below a division.
+    fn build(&self, coefficient: &BigRational) -> Expression {
+        if self.factors.is_empty() {
+            return rational_expr(coefficient);
+        }
+
+        let mut numerator = vec![];
+        let mut denominator = vec![];
+        for factor in &self.factors {
+            match rational(&factor.exponent) {
+                Some(exponent) if exponent.is_negative() => {
+                    denominator.push(power(factor.base.clone(), rational_expr(&-exponent)))
+                }
+                _ => numerator.push(power(factor.base.clone(), factor.exponent.clone())),
+            }
+        }
+
+        let magnitude = coefficient.abs();
+        if !magnitude.numer().is_one() || numerator.is_empty() {
+            numerator.insert(0, integer(magnitude.numer()));
+        }
+        if !magnitude.denom().is_one() {
+            denominator.insert(0, integer(magnitude.denom()));
+        }
+        if coefficient.is_negative() {
+            numerator[0] = Expression::unary(UnaryOperation::Negate, numerator[0].clone());
+        }
+
+        let numerator = product(numerator);
+        if denominator.is_empty() {
+            numerator
+        } else {
+            Expression::binary(BinaryOperation::Divide, numerator, product(denominator))
+        }
+    }
+}
+
+/// Whether the expression is known to be a scalar: numbers, variables `ctx`
+/// never bound to a vector or matrix, and what is computed from them.
+pub(super) fn is_scalar(expr: &Expression, ctx: &Context) -> bool {
+    match expr {
+        Expression::Literal { content, .. } => {
+            if content.parse::<f64>().is_ok() {
+                return true;
+            }
+            let value = match content.starts_with("@") {
+                true => GlobalContext::resolve_variable(content),
+                false => ctx.resolve_variable(content),
+            };
+            matches!(value, None | Some(Value::Number(_) | Value::Boolean(_)))
+        }
+        Expression::Parenthesis { expr, .. } | Expression::GraphExpression { expr } => is_scalar(expr, ctx),
+        Expression::Unary { operation, expr, .. } => match operation {
+            UnaryOperation::Determinant | UnaryOperation::Rank | UnaryOperation::Trace | UnaryOperation::Norm => true,
+            UnaryOperation::LowerUpper | UnaryOperation::QR | UnaryOperation::Eigen | UnaryOperation::SingularValues => {
+                false
+            }
+            _ => is_scalar(expr, ctx),
+        },
+        Expression::Binary { op, lhs, rhs, .. } => match op {
+            BinaryOperation::Dot | BinaryOperation::Angle => true,
+            BinaryOperation::LinearSolve
+            | BinaryOperation::Cross
+            | BinaryOperation::Project
+            | BinaryOperation::Store
+            | BinaryOperation::Invoke => false,
+            _ => is_scalar(lhs, ctx) && is_scalar(rhs, ctx),
+        },
+        Expression::Summation { expression, .. } | Expression::Product { expression, .. } => {
+            is_scalar(expression, ctx)
+        }
+        Expression::Integral { integrand, .. } => is_scalar(integrand, ctx),
+        Expression::Derivative { expression, .. } => is_scalar(expression, ctx),
+        Expression::Solve { .. } => true,
+        Expression::Conditional {
+            consequent,
+            alternative,
+            ..
+        } => is_scalar(consequent, ctx) && is_scalar(alternative, ctx),
+        Expression::Vector { .. } | Expression::Roots { .. } | Expression::Lambda { .. } => false,
+    }
+}
+
+fn add_exponents(lhs: &Expression, rhs: &Expression, ctx: &Context) -> Expression {
+    match (rational(lhs), rational(rhs)) {
+        (Some(lhs), Some(rhs)) => rational_expr(&(lhs + rhs)),
+        _ => Expression::binary(BinaryOperation::Add, lhs.clone(), rhs.clone()).simplify_once(ctx),
+    }
+}
+
+fn scale_exponent(exponent: &Expression, power: &BigRational, ctx: &Context) -> Expression {
+    if power.is_one() {
+        return exponent.clone();
+    }
+    match rational(exponent) {
+        Some(exponent) => rational_expr(&(exponent * power)),
+        None => Expression::binary(BinaryOperation::Multiply, rational_expr(power), exponent.clone()).simplify_once(ctx),
+    }
+}
+
+pub(super) fn power(base: Expression, exponent: Expression) -> Expression {
+    if rational(&exponent).is_some_and(|exponent| exponent.is_one()) {
+        return base;
+    }
+    Expression::binary(BinaryOperation::Power, base, exponent)
+}
+
+pub(super) fn product(factors: Vec<Expression>) -> Expression {
+    factors
+        .into_iter()
+        .reduce(|lhs, rhs| Expression::binary(BinaryOperation::Multiply, lhs, rhs))
+        .unwrap_or_else(|| Expression::number(1.0))
+}
+
+/// Whether `value^exponent` can be multiplied out into a coefficient.
+fn foldable_power(value: &BigRational, exponent: &BigRational) -> Option<i64> {
+    let exponent = exponent.is_integer().then(|| exponent.to_integer().to_i64())??;
+    let in_range = exponent.abs() <= MAX_FOLDED_POWER && !(value.is_zero() && exponent < 0);
+    in_range.then_some(exponent)
+}
+
+fn pow(value: &BigRational, exponent: i64) -> BigRational {
+    let result = (0..exponent.unsigned_abs()).fold(BigRational::one(), |result, _| result * value);
+    if exponent < 0 {
+        result.recip()
+    } else {
+        result
+    }
+}
+
+/// Reads an exact rational out of a number literal, or a negation or
+/// division of them.
+pub(super) fn rational(expr: &Expression) -> Option<BigRational> {
+    match expr {
+        Expression::Literal { content, .. } => {
+            let (whole, fraction) = content.split_once('.').unwrap_or((content, ""));
+            if whole.is_empty() && fraction.is_empty()
+                || !whole.chars().chain(fraction.chars()).all(|c| c.is_ascii_digit())
+            {
+                return None;
+            }
+            let digits: BigInt = format!("{}{}", whole, fraction).parse().ok()?;
+            Some(BigRational::new(digits, BigInt::from(10).pow(fraction.len() as u32)))
+        }
+        Expression::Unary {
+            operation: UnaryOperation::Negate,
+            expr,
+            ..
+        } => rational(expr).map(|value| -value),
+        Expression::Parenthesis { expr, .. } => rational(expr),
+        Expression::Binary {
+            op: BinaryOperation::Divide,
+            lhs,
+            rhs,
+            ..
+        } => {
+            let rhs = rational(rhs).filter(|rhs| !rhs.is_zero())?;
+            Some(rational(lhs)? / rhs)
+        }
+        _ => None,
+    }
+}
+
+/// Writes a rational as a decimal when it terminates and as a division otherwise.
+pub(super) fn rational_expr(value: &BigRational) -> Expression {
+    if value.is_negative() {
+        return Expression::unary(UnaryOperation::Negate, rational_expr(&-value));
+    }
+    if value.is_integer() {
+        return integer(value.numer());
+    }
+
+    let mut denominator = value.denom().clone();
+    let mut digits = 0;
+    for prime in [2, 5] {
+        let prime = BigInt::from(prime);
+        let mut count = 0;
+        while (&denominator % &prime).is_zero() {
+            denominator /= &prime;
+            count += 1;
+        }
+        digits = count.max(digits);
+    }
+    if !denominator.is_one() {
+        return Expression::binary(
+            BinaryOperation::Divide,
+            integer(value.numer()),
+            integer(value.denom()),
+        );
+    }
+
+    let scaled = (value * BigRational::from_integer(BigInt::from(10).pow(digits))).to_integer();
+    let text = format!("{:0>width$}", scaled, width = digits as usize + 1);
+    let (whole, fraction) = text.split_at(text.len() - digits as usize);
+    Expression::variable(&format!("{}.{}", whole, fraction))
+}
+
+pub(super) fn integer(value: &BigInt) -> Expression {
+    Expression::variable(&value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math::context::Context;
+    use crate::math::expr::Expression;
+
+    fn evaluate(expr: &Expression) -> String {
+        expr.eval(&mut Context::default()).to_string()
+    }
+
+    fn simplified(source: &str) -> String {
+        Expression::parse(source).unwrap().simplify(&Context::default()).to_string()
+    }
+
+    #[test]
+    fn collects_like_terms() {
+        assert_eq!(simplified("x + x"), "2 * x");
+        assert_eq!(simplified("3 - 5 * x + 2 * x"), "-3 * x + 3");
+        assert_eq!(simplified("(x + 1) - (x - 1)"), "2");
+        assert_eq!(simplified("0 + 1 * y"), "y");
+    }
+
+    #[test]
+    fn merges_powers_of_the_same_base() {
+        assert_eq!(simplified("2 * x * 3 * x"), "6 * x^2");
+        assert_eq!(simplified("x^2 * x^3 / x"), "x^4");
+        assert_eq!(simplified("x / x"), "1");
+        assert_eq!(simplified("2^(1/2) * 2^(1/2)"), "2");
+    }
+
+    #[test]
+    fn replaces_derivatives() {
+        assert_eq!(simplified("derive(x, x^3)"), "3 * x^2");
+    }
+
+    #[test]
+    fn matrix_products_keep_their_order() {
+        for source in [
+            "[[1, 2], [3, 4]] * [[0, 1], [1, 0]] - [[0, 1], [1, 0]] * [[1, 2], [3, 4]]",
+            "[[1, 2], [3, 4]] * [[0, 1], [1, 0]] / [[1, 2], [3, 4]]",
+            "2 * [[1, 2], [3, 4]] * 3 * [[0, 1], [1, 0]]",
+            "([[1, 2], [3, 4]] + [[0, 1], [1, 0]])^2",
+        ] {
+            let expr = Expression::parse(source).unwrap();
+            assert_eq!(evaluate(&expr.simplify(&Context::default())), evaluate(&expr), "simplifying {}", source);
+            assert_eq!(evaluate(&expr.expand(&Context::default())), evaluate(&expr), "expanding {}", source);
+            assert_eq!(evaluate(&expr.factor(&Context::default())), evaluate(&expr), "factoring {}", source);
+        }
+        let commutator = Expression::parse("[[1, 2], [3, 4]] * [[0, 1], [1, 0]] - [[0, 1], [1, 0]] * [[1, 2], [3, 4]]");
+        assert_ne!(commutator.unwrap().simplify(&Context::default()).to_string(), "0");
+    }
+
+    #[test]
+    fn matrix_variables_keep_their_order() {
+        let mut ctx = Context::default();
+        for definition in ["A = [[1, 2], [3, 4]]", "B = [[0, 1], [1, 0]]", "v = [1, 2]"] {
+            Expression::parse(definition).unwrap().eval(&mut ctx);
+        }
+        for source in ["A * B - B * A", "A * B / A", "2 * A * 3 * B", "(A + B)^2", "(A + 1) * (A - 1)", "v * v"] {
+            let expr = Expression::parse(source).unwrap();
+            let expected = expr.eval(&mut ctx.clone()).to_string();
+            for (name, result) in [
+                ("simplifying", expr.simplify(&ctx)),
+                ("expanding", expr.expand(&ctx)),
+                ("factoring", expr.factor(&ctx)),
+            ] {
+                assert_eq!(result.eval(&mut ctx.clone()).to_string(), expected, "{} {}", name, source);
+            }
+        }
+        let commutator = Expression::parse("A * B - B * A").unwrap();
+        assert_ne!(commutator.simplify(&ctx).to_string(), "0");
+        assert_eq!(simplified("x * y - y * x"), "0");
+    }
+}
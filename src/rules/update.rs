@@ -1,4 +1,4 @@
-use crate::gui::idx::new_id;
+use crate::math::idx::new_id;
 use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
 
 impl Expression {
@@ -13,6 +13,50 @@ impl Expression {
                 lhs.update();
                 rhs.update();
 
+                // A comma after an argument starts the next one, currying the call.
+                if *op == BinaryOperation::Invoke {
+                    if let Expression::Literal { content, .. } = &mut **rhs {
+                        if content.len() > 1 && content.ends_with(",") {
+                            content.pop();
+                            let call = self.clone();
+                            *self = Expression::Binary {
+                                op: BinaryOperation::Invoke,
+                                lhs: Box::new(call),
+                                rhs: Box::new(Expression::Literal {
+                                    content: "".to_string(),
+                                    id: new_id(),
+                                    new_literal: true,
+                                }),
+                                id: new_id(),
+                            };
+                            return;
+                        }
+                    }
+                }
+
+                // Typing `=` after the last parameter of `f(x` defines the function `f(x) = ...`.
+                if *op == BinaryOperation::Invoke {
+                    if let Expression::Binary {
+                        op: BinaryOperation::Store,
+                        lhs: parameter,
+                        rhs: body,
+                        ..
+                    } = &**rhs
+                    {
+                        if matches!(**parameter, Expression::Literal { .. }) {
+                            let body = body.clone();
+                            *rhs = parameter.clone();
+                            *self = Expression::Binary {
+                                op: BinaryOperation::Store,
+                                lhs: Box::new(self.clone()),
+                                rhs: body,
+                                id: new_id(),
+                            };
+                            return;
+                        }
+                    }
+                }
+
                 if let Expression::Literal {
                     content: lhs, id, ..
                 } = *lhs.clone()
@@ -67,10 +111,15 @@ impl Expression {
                 }
                 _ if content.ends_with("*") => self.build_binop(BinaryOperation::Multiply, "*"),
                 _ if content.ends_with("/") => self.build_binop(BinaryOperation::Divide, "/"),
+                _ if content.ends_with("!") => self.build_postfix(UnaryOperation::Factorial, "!"),
+                _ if content.ends_with("mod") => self.build_binop(BinaryOperation::Modulo, "mod"),
+                _ if content.ends_with("div") => self.build_binop(BinaryOperation::IntegerDivide, "div"),
                 _ if content.ends_with("^") => self.build_binop(BinaryOperation::Power, "^"),
                 _ if content.ends_with("=") => self.build_binop(BinaryOperation::Store, "="),
+                _ if content.starts_with("sqrt") => self.build_unop(UnaryOperation::SquareRoot),
                 _ if content.ends_with("rt") => self.build_binop(BinaryOperation::Root, "rt"),
-                _ if content.ends_with("root") => self.build_binop(BinaryOperation::Root, "root"),
+                // A bare `root` waits, as it may still become `roots(`.
+                _ if content.ends_with("root") && content != "root" => self.build_binop(BinaryOperation::Root, "root"),
                 _ if content.starts_with("(") && content.ends_with("(") => {
                     *self = Expression::Parenthesis {
                         expr: Box::new(Expression::Literal {
@@ -82,6 +131,20 @@ impl Expression {
                         unbox_to_binop: false
                     }
                 }
+                _ if content.starts_with("matrix") => {
+                    let entry = |new_literal| Expression::Literal {
+                        content: "".to_string(),
+                        id: new_id(),
+                        new_literal,
+                    };
+                    *self = Expression::Vector {
+                        exprs: vec![
+                            Expression::Vector { exprs: vec![entry(true), entry(false)], id: new_id() },
+                            Expression::Vector { exprs: vec![entry(false), entry(false)], id: new_id() },
+                        ],
+                        id: new_id(),
+                    }
+                }
                 _ if content.starts_with("[") => {
                     *self = Expression::Vector {
                         exprs: Vec::new(),
@@ -121,12 +184,148 @@ impl Expression {
                         }),
                     }
                 }
+                _ if content.ends_with("prod") => {
+                    *self = Expression::Product {
+                        minimum: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                        maximum: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                        variable: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                        expression: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                    }
+                }
+                _ if content.starts_with("solve") => {
+                    *self = Expression::Solve {
+                        minimum: Box::new(Expression::Literal {
+                            content: "-10".to_string(),
+                            id: new_id(),
+                            new_literal: false,
+                        }),
+                        maximum: Box::new(Expression::Literal {
+                            content: "10".to_string(),
+                            id: new_id(),
+                            new_literal: false,
+                        }),
+                        variable: Box::new(Expression::Literal {
+                            content: "x".to_string(),
+                            id: new_id(),
+                            new_literal: false,
+                        }),
+                        equation: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                    }
+                }
+                _ if content.starts_with("int") => {
+                    *self = Expression::Integral {
+                        lower: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                        upper: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                        variable: Box::new(Expression::Literal {
+                            content: "x".to_string(),
+                            id: new_id(),
+                            new_literal: false,
+                        }),
+                        integrand: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                    }
+                }
+                _ if content.starts_with("if") => {
+                    *self = Expression::Conditional {
+                        condition: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                        consequent: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: false,
+                        }),
+                        alternative: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: false,
+                        }),
+                    }
+                }
+                _ if content == "roots(" => {
+                    *self = Expression::Roots {
+                        polynomial: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                        variable: Box::new(Expression::Literal {
+                            content: "x".to_string(),
+                            id: new_id(),
+                            new_literal: false,
+                        }),
+                    }
+                }
+                _ if content.starts_with("derive") => {
+                    *self = Expression::Derivative {
+                        variable: Box::new(Expression::Literal {
+                            content: "x".to_string(),
+                            id: new_id(),
+                            new_literal: false,
+                        }),
+                        expression: Box::new(Expression::Literal {
+                            content: "?".to_string(),
+                            id: new_id(),
+                            new_literal: true,
+                        }),
+                    }
+                }
                 _ if content.starts_with("fn") => *self = Expression::Lambda {
-                    variable: Box::new(Expression::Literal { content: "x".to_string(), id: new_id(),
-                        new_literal: true, }),
+                    variables: vec![Expression::Literal { content: "x".to_string(), id: new_id(),
+                        new_literal: true, }],
                     expr: Box::new(Expression::Literal { content: "x".to_string(), id: new_id(),
                         new_literal: true, }),
                 },
+                // `gamma` and `beta` have already been rewritten to their symbols by now.
+                _ if content == "γ(" => self.build_unop(UnaryOperation::Gamma),
+                _ if content == "lγ(" => self.build_unop(UnaryOperation::LogGamma),
+                _ if content == "diγ(" => self.build_unop(UnaryOperation::Digamma),
+                _ if content == "β(" => self.build_bracketed(BinaryOperation::Beta),
+                // Short names wait for the bracket, so variables may still start with them.
+                _ if content == "lu(" => self.build_unop(UnaryOperation::LowerUpper),
+                _ if content == "qr(" => self.build_unop(UnaryOperation::QR),
+                _ if content == "eig(" => self.build_unop(UnaryOperation::Eigen),
+                _ if content == "svd(" => self.build_unop(UnaryOperation::SingularValues),
+                _ if content == "dot(" => self.build_bracketed(BinaryOperation::Dot),
+                _ if content == "norm(" => self.build_unop(UnaryOperation::Norm),
+                _ if content == "unit(" => self.build_unop(UnaryOperation::Unit),
+                _ if content == "proj(" => self.build_bracketed(BinaryOperation::Project),
+                // `exp` waits too, or `expm` could never be typed.
+                _ if content == "exp(" => self.build_unop(UnaryOperation::Exp),
+                _ if content == "expm(" => self.build_unop(UnaryOperation::MatrixExp),
                 _ if content.ends_with("(") && !content.starts_with("(") => self.build_binop(BinaryOperation::Invoke, "("),
                 _ if content.starts_with("sin") => self.build_unop(UnaryOperation::Sin),
                 _ if content.starts_with("cos") => self.build_unop(UnaryOperation::Cos),
@@ -134,6 +333,24 @@ impl Expression {
                 _ if content.starts_with("asin") => self.build_unop(UnaryOperation::InverseSin),
                 _ if content.starts_with("acos") => self.build_unop(UnaryOperation::InverseCos),
                 _ if content.starts_with("atan") => self.build_unop(UnaryOperation::InverseTan),
+                _ if content.starts_with("ln") => self.build_unop(UnaryOperation::NaturalLog),
+                _ if content.starts_with("log10") => self.build_unop(UnaryOperation::Log10),
+                _ if content.starts_with("log_") => self.build_bracketed(BinaryOperation::Log),
+                _ if content.starts_with("abs") => self.build_unop(UnaryOperation::Abs),
+                _ if content.starts_with("round") => self.build_unop(UnaryOperation::Round),
+                _ if content.starts_with("floor") => self.build_unop(UnaryOperation::Floor),
+                _ if content.starts_with("ceil") => self.build_unop(UnaryOperation::Ceil),
+                _ if content.starts_with("sgn") => self.build_unop(UnaryOperation::Sign),
+                _ if content.starts_with("frac") => self.build_unop(UnaryOperation::Fractional),
+                _ if content.starts_with("transpose") => self.build_unop(UnaryOperation::Transpose),
+                _ if content.starts_with("det") => self.build_unop(UnaryOperation::Determinant),
+                _ if content.starts_with("inv") => self.build_unop(UnaryOperation::Inverse),
+                _ if content.starts_with("rank") => self.build_unop(UnaryOperation::Rank),
+                _ if content.starts_with("trace") => self.build_unop(UnaryOperation::Trace),
+                // `solve` already builds the equation solver.
+                _ if content.starts_with("linsolve") => self.build_bracketed(BinaryOperation::LinearSolve),
+                _ if content.starts_with("cross") => self.build_bracketed(BinaryOperation::Cross),
+                _ if content.starts_with("angle") => self.build_bracketed(BinaryOperation::Angle),
                 _ => {
                     *content = content
                         .replace("pi", "π")
@@ -181,6 +398,24 @@ impl Expression {
                 maximum,
                 variable,
                 expression,
+            }
+            | Expression::Product {
+                minimum,
+                maximum,
+                variable,
+                expression,
+            }
+            | Expression::Solve {
+                minimum,
+                maximum,
+                variable,
+                equation: expression,
+            }
+            | Expression::Integral {
+                lower: minimum,
+                upper: maximum,
+                variable,
+                integrand: expression,
             } => {
                 minimum.update();
                 maximum.update();
@@ -227,11 +462,18 @@ impl Expression {
                     }
                 }
             }
-            Expression::Lambda { variable, expr, .. } => {
+            Expression::Derivative {
+                variable,
+                expression,
+            }
+            | Expression::Roots {
+                polynomial: expression,
+                variable,
+            } => {
                 variable.update();
-                expr.update();
+                expression.update();
                 if let Expression::Literal { content: ref v_content, .. } = **variable {
-                    if let Expression::Literal { content: ref e_content, .. } = **expr {
+                    if let Expression::Literal { content: ref e_content, .. } = **expression {
                         if v_content.is_empty() && e_content.is_empty() {
                             *self = Expression::Literal {
                                 content: "".to_string(),
@@ -242,6 +484,79 @@ impl Expression {
                     }
                 }
             }
+            Expression::Conditional {
+                condition,
+                consequent,
+                alternative,
+            } => {
+                condition.update();
+                consequent.update();
+                alternative.update();
+                let is_empty = |expr: &Expression| matches!(expr, Expression::Literal { content, .. } if content.is_empty());
+                if is_empty(condition) && is_empty(consequent) && is_empty(alternative) {
+                    *self = Expression::Literal {
+                        content: "".to_string(),
+                        id: new_id(),
+                        new_literal: true,
+                    };
+                }
+            }
+            Expression::Lambda { variables, expr, .. } => {
+                for variable in &mut *variables {
+                    variable.update();
+                }
+                expr.update();
+                let parameters_empty = variables
+                    .iter()
+                    .all(|variable| matches!(variable, Expression::Literal { content, .. } if content.is_empty()));
+                if parameters_empty {
+                    if let Expression::Literal { content: ref e_content, .. } = **expr {
+                        if e_content.is_empty() {
+                            *self = Expression::Literal {
+                                content: "".to_string(),
+                                id: new_id(),
+                                new_literal: true,
+                            };
+                        }
+                    }
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::math::expr::{BinaryOperation, Expression, UnaryOperation};
+
+    /// Types `text` into a new literal one character at a time, updating after each.
+    fn type_out(text: &str) -> Expression {
+        let mut expr = Expression::variable("");
+        for c in text.chars() {
+            if let Expression::Literal { content, .. } = &mut expr {
+                content.push(c);
+            }
+            expr.update();
+        }
+        expr
+    }
+
+    #[test]
+    fn exponentials_wait_for_the_bracket() {
+        for (text, operation) in [("exp(", UnaryOperation::Exp), ("expm(", UnaryOperation::MatrixExp)] {
+            let Expression::Unary { operation: typed, .. } = type_out(text) else {
+                panic!("`{}` did not build a function", text);
+            };
+            assert_eq!(typed, operation);
+        }
+        assert!(matches!(type_out("expo"), Expression::Literal { .. }));
+    }
+
+    #[test]
+    fn roots_can_be_typed_out() {
+        assert!(matches!(type_out("roots("), Expression::Roots { .. }));
+        assert!(matches!(type_out("root"), Expression::Literal { .. }));
+        assert!(matches!(type_out("3root"), Expression::Binary { op: BinaryOperation::Root, .. }));
+        assert!(!matches!(type_out("zeros("), Expression::Roots { .. }));
+    }
+}